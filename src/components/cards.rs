@@ -1,45 +1,41 @@
 use bevy::prelude::*;
 
-#[derive(Reflect, Clone, Copy, Debug)]
-pub enum CardSuit {
-    Hearts,
-    Spades,
-    Diamonds,
-    Clubs,
-}
-#[derive(Reflect, Clone, Copy, Debug)]
-pub enum CardColor {
-    Red,
-    Black,
-}
+use crate::rules::Card;
 
-#[derive(Component, Reflect, Clone, Copy, Debug)]
-pub struct CardVisual {
-    pub index: usize,
-    pub number: usize,
-    pub suit: CardSuit,
-    pub color: CardColor,
-}
+/// A card on the table, drawn from the `Card` the rules know it as.
+#[derive(Component, Deref, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CardVisual(pub Card);
+
+/// Where a card belongs on the table, the spot in its pile the board sync worked out. Its
+/// `Transform` is where it is right now, `MoveThisCard` carries it from one to the other and a
+/// drag lifts it off until it's dropped.
 #[derive(Component, Default, Reflect, Clone, Copy, Debug)]
-pub struct CardDraggable {
-    pub card: Option<Entity>,
-}
+pub struct CardTarget(pub Vec2);
 
 #[derive(Component, Reflect, Clone, Copy, Debug)]
 pub struct CardSlot;
 
+/// Which pile a card is in, the pile's `CardSlot` entity, and how far up it sits. The board sync
+/// keeps it in step with the game and the card's depth on screen comes from it.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InPile {
+    pub pile: Entity,
+    pub index: usize,
+}
+
+/// The cards in a pile, bottom first, on the pile's `CardSlot` entity.
+#[derive(Component, Default, Clone, Debug)]
+pub struct PileCards(pub Vec<Entity>);
+
 #[derive(Component)]
 pub struct CardFront;
 #[derive(Component)]
 pub struct CardBack;
 
-#[derive(Resource, Reflect, Default)]
+#[derive(Resource, Default)]
 pub struct Cards {
     pub cards: Vec<CardVisual>,
 }
 
-#[derive(Resource)]
-pub struct CurrentCard(pub usize);
-
 pub const CARD_SIZE: Vec2 = Vec2::new(53.0, 70.0);
 
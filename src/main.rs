@@ -1,53 +1,267 @@
 use std::usize;
 
-use bevy::{prelude::KeyCode, prelude::*};
+use bevy::{ecs::system::SystemParam, prelude::KeyCode, prelude::*};
 use bevy_egui::EguiPlugin;
 use bevy_inspector_egui::DefaultInspectorConfigPlugin;
 mod components;
 mod inspector;
+mod rules;
 mod systems;
 use bevy_window::PrimaryWindow;
-use rand::Rng;
+use rules::variant::Variant;
 
 fn main() {
+    let mut args = std::env::args().skip(1).peekable();
+    let solving = args.next_if(|a| a == "solve").is_some();
+    let starting = match parse_args(args) {
+        Ok(starting) => starting,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!(
+                "usage: simplegame [--game <klondike|spider|freecell|pyramid|golf|tripeaks> | --game-file <variant.ron>] [--suits <1|2|4>] [--deal <number>] [--seed <u64 or 0x hex>] [--draw <1|3>] [--passes <n>] [--scoring <standard|vegas|vegas-cumulative>] [--layout <auto|landscape|portrait>] [--winnable]"
+            );
+            eprintln!(
+                "       simplegame solve (--deal <number> | --seed <u64 or 0x hex>) [--draw <1|3>] [--passes <n>] [--budget <positions>]"
+            );
+            std::process::exit(2);
+        }
+    };
+    if solving {
+        std::process::exit(solve_from_command_line(&starting));
+    }
     App::new()
         .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
         .add_plugins(EguiPlugin)
         .add_plugins(DefaultInspectorConfigPlugin)
         .add_plugins(inspector::InspectorPlugin)
         .add_plugins(systems::cards::CardsPlugin)
-        .register_type::<components::cards::CardSuit>()
-        .register_type::<components::cards::CardColor>()
-        .register_type::<components::cards::CardVisual>()
+        .add_plugins(systems::board::BoardPlugin)
+        .add_plugins(systems::focus::FocusPlugin)
+        .add_plugins(systems::gamepad::GamepadPlugin)
+        .add_plugins(systems::hint::HintPlugin)
+        .add_plugins(systems::hud::HudPlugin)
+        .add_plugins(systems::pause::PausePlugin)
+        .add_plugins(systems::save::SavePlugin)
+        .add_plugins(systems::score::ScorePlugin)
+        .add_plugins(systems::table::TablePlugin)
+        .add_plugins(systems::win::WinPlugin)
         .register_type::<MoveThisCard>()
         .register_type::<u128>()
-        .register_type::<CardSlotPositions>()
         .insert_resource(components::cards::Cards { cards: Vec::new() })
         .insert_resource(LastClickedEntity(None))
-        .insert_resource(Dragging {
-            draggable: None,
-            offset: Vec2::ZERO,
-            card_start_position: Vec2::ZERO,
-            card_draggable: components::cards::CardDraggable { card: None },
-            card_id: None,
-        })
-        .insert_resource(components::cards::CurrentCard(0))
+        .init_resource::<Dragging>()
+        .insert_resource(starting.scoring.unwrap_or_default())
+        .insert_resource(systems::board::WinnableOnly(starting.winnable_only))
+        .insert_resource(starting.layout)
+        .insert_resource(starting)
         .init_resource::<MousePosition>()
-        .add_systems(Startup, (setup, generate_board))
+        .add_systems(
+            Startup,
+            (
+                start_game,
+                apply_deferred,
+                generate_board,
+                apply_deferred,
+                setup,
+            )
+                .chain(),
+        )
         .add_systems(
             Update,
             (
                 move_cards,
                 keyboard_input,
+                debug_keys,
                 mouse_input,
                 mouse_position_system,
                 drag,
-                card_stacking,
+                card_depth.after(move_cards).after(drag),
             ),
         )
         .run();
 }
 
+/// What the command line asked for. Without a deal, a game or any of the rules `start_game` picks
+/// up the saved game, or a random Klondike deal if there isn't one.
+#[derive(Resource)]
+struct StartingDeal {
+    deal: Option<rules::shuffle::DealSeed>,
+    /// Set by `--game`, with the other options for that game already folded in.
+    variant: Option<Variant>,
+    stock_rules: rules::klondike::StockRules,
+    /// Set by `--scoring`. The saved game only gets picked up if it was being scored this way.
+    scoring: Option<systems::score::ScoreSettings>,
+    layout: systems::table::LayoutMode,
+    /// Deal random games the solver has found a way to win.
+    winnable_only: bool,
+    /// Set by `--draw`, `--passes` and `--winnable`. The saved game was dealt under whatever they
+    /// were last time, so a fresh one gets dealt instead.
+    rules_given: bool,
+    /// Positions `solve` gets to look at before giving up.
+    solver_budget: usize,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<StartingDeal, String> {
+    let mut starting = StartingDeal {
+        deal: None,
+        variant: None,
+        stock_rules: default(),
+        scoring: None,
+        layout: default(),
+        winnable_only: false,
+        rules_given: false,
+        solver_budget: 1_000_000,
+    };
+    let mut game = None;
+    let mut game_file = None;
+    let mut suits = 1;
+    while let Some(arg) = args.next() {
+        if arg == "--winnable" {
+            starting.winnable_only = true;
+            starting.rules_given = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        starting.rules_given |= matches!(arg.as_str(), "--draw" | "--passes");
+        match arg.as_str() {
+            "--deal" => {
+                starting.deal = Some(format!("#{}", value.trim_start_matches('#')).parse()?)
+            }
+            "--seed" => starting.deal = Some(value.parse()?),
+            "--game" => match value.as_str() {
+                "klondike" | "spider" | "freecell" | "pyramid" | "golf" | "tripeaks" => {
+                    game = Some(value)
+                }
+                _ => return Err(format!("unknown game {}", value)),
+            },
+            "--game-file" => {
+                let definition = rules::custom::load(std::path::Path::new(&value))
+                    .map_err(|e| format!("{}: {}", value, e))?;
+                game_file = Some(std::sync::Arc::new(definition));
+            }
+            "--suits" => {
+                suits = match value.as_str() {
+                    "1" => 1,
+                    "2" => 2,
+                    "4" => 4,
+                    _ => return Err(format!("--suits takes 1, 2 or 4, not {}", value)),
+                }
+            }
+            "--draw" => {
+                starting.stock_rules.draw = match value.as_str() {
+                    "1" => 1,
+                    "3" => 3,
+                    _ => return Err(format!("--draw takes 1 or 3, not {}", value)),
+                }
+            }
+            "--passes" => {
+                let passes = value
+                    .parse()
+                    .map_err(|e| format!("bad --passes {:?}: {}", value, e))?;
+                starting.stock_rules.max_passes = Some(passes);
+            }
+            "--scoring" => {
+                use rules::scoring::Scoring;
+                let (scoring, cumulative) = match value.as_str() {
+                    "standard" => (Scoring::Standard, false),
+                    "vegas" => (Scoring::Vegas, false),
+                    "vegas-cumulative" => (Scoring::Vegas, true),
+                    _ => return Err(format!("unknown scoring {}", value)),
+                };
+                starting.scoring = Some(systems::score::ScoreSettings {
+                    scoring,
+                    cumulative,
+                });
+            }
+            "--layout" => {
+                use systems::table::LayoutMode;
+                starting.layout = match value.as_str() {
+                    "auto" => LayoutMode::Auto,
+                    "landscape" => LayoutMode::Landscape,
+                    "portrait" => LayoutMode::Portrait,
+                    _ => return Err(format!("unknown layout {}", value)),
+                };
+            }
+            "--budget" => {
+                starting.solver_budget = value
+                    .parse()
+                    .map_err(|e| format!("bad --budget {:?}: {}", value, e))?;
+            }
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+    if game.is_some() && game_file.is_some() {
+        return Err("--game and --game-file don't go together".to_string());
+    }
+    if let Some(definition) = game_file {
+        starting.variant = Some(Variant::Custom(definition));
+        return Ok(starting);
+    }
+    // after the loop so --draw and --suits can come either side of --game
+    starting.variant = game.map(|game| match game.as_str() {
+        "spider" => Variant::Spider { suits },
+        "freecell" => Variant::FreeCell,
+        "pyramid" => Variant::Overlap(rules::overlap::Rules::Pyramid),
+        "golf" => Variant::Overlap(rules::overlap::Rules::Golf),
+        "tripeaks" => Variant::Overlap(rules::overlap::Rules::TriPeaks),
+        _ => Variant::Klondike(starting.stock_rules),
+    });
+    Ok(starting)
+}
+
+impl StartingDeal {
+    /// What to deal when there's nothing to restore.
+    fn new_variant(&self) -> Variant {
+        self.variant
+            .clone()
+            .unwrap_or(Variant::Klondike(self.stock_rules))
+    }
+}
+
+/// `simplegame solve` checks a deal without opening a window. The exit code is 0 when the deal
+/// can be won, 1 when it can't and 3 when the solver gave up, so scripts can keep an eye on hard
+/// deals.
+fn solve_from_command_line(starting: &StartingDeal) -> i32 {
+    use rules::solver::{self, Verdict};
+    let Some(deal) = starting.deal else {
+        eprintln!("solve needs a --deal or a --seed");
+        return 2;
+    };
+    let Variant::Klondike(stock_rules) = starting.new_variant() else {
+        eprintln!("solve only knows Klondike");
+        return 2;
+    };
+    let report = solver::solve_deal(deal, stock_rules, starting.solver_budget);
+    match report.verdict {
+        Verdict::Solvable(moves) => {
+            println!(
+                "Deal {} can be won in {} moves, {} positions searched",
+                deal,
+                moves.len(),
+                report.nodes
+            );
+            for mv in moves {
+                println!("  {:?}", mv);
+            }
+            0
+        }
+        Verdict::NoSolutionFound => {
+            println!(
+                "Found no way to win deal {} in {} positions, though the search skips moves it \
+                 doesn't expect to help so there might still be one",
+                deal, report.nodes
+            );
+            1
+        }
+        Verdict::Unknown => {
+            println!("Gave up on deal {} after {} positions", deal, report.nodes);
+            3
+        }
+    }
+}
+
 #[derive(Resource, Default)]
 struct MousePosition(Vec2);
 
@@ -68,114 +282,118 @@ fn mouse_position_system(
 }
 
 #[derive(Resource, Default)]
-struct LastClickedEntity(Option<Entity>);
+struct LastClickedEntity(Option<(Entity, f32)>);
 
-// Really gotta clean up this struct, I think the way I'm using this, card_id and draggable are the
-// same entity.
+/// Two clicks on the same card closer together than this are a double click.
+const DOUBLE_CLICK_SECS: f32 = 0.4;
+
+/// The drag in progress, if any. `cards` is the grabbed card followed by everything stacked on
+/// top of it, in pile order, and `offset` is from where the grabbed card belongs to the cursor.
 #[derive(Resource, Default)]
 struct Dragging {
-    draggable: Option<Entity>,
+    pile: Option<rules::PileId>,
     offset: Vec2,
-    card_start_position: Vec2,
-    card_id: Option<Entity>,
-    card_draggable: components::cards::CardDraggable,
+    cards: Vec<Entity>,
 }
 
+/// How deep a card gets drawn while it's held, above anything `card_depth` gives a card in a pile.
+const DRAGGING_Z: f32 = 1000.0;
+
+/// The held cards follow the cursor keeping the spacing they have in their pile, their targets
+/// stay put so an illegal drop has somewhere to go back to.
 fn drag(
     pos: Res<MousePosition>,
-    last_clicked: Res<Dragging>,
-    mut draggables: Query<(Entity, &mut Transform, &components::cards::CardDraggable)>,
+    dragging: Res<Dragging>,
+    mut cards: Query<(&mut Transform, &components::cards::CardTarget)>,
 ) {
-    if last_clicked.draggable.is_none() {
+    let Some(&grabbed) = dragging.cards.first() else {
         return;
+    };
+    let Ok((_, &components::cards::CardTarget(grabbed_at))) = cards.get(grabbed) else {
+        return;
+    };
+    let f = pos.0 - dragging.offset;
+    for &held in dragging.cards.iter() {
+        let (mut card_tx, target) = cards
+            .get_mut(held)
+            .expect("Card saved in Dragging doesn't have a CardTarget.");
+        let spacing = target.0 - grabbed_at;
+        card_tx.translation = (f + spacing).extend(card_tx.translation.z);
     }
-    let (_drag_ent, mut drag_tx, _drag_able) = draggables
-        .iter_mut()
-        .find(|x| Some(x.0) == last_clicked.draggable)
-        .expect("Draggable saved in Dragging doesnt match any CardDraggables queried.");
-    let f = pos.0 - last_clicked.offset;
-    drag_tx.translation = f.extend(drag_tx.translation.z); //Vec3::new(f.x, f.y, 0.0);
 }
 
 #[derive(Component)]
 struct Clickable;
 
-#[derive(Reflect, Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug)]
 struct Slot {
+    pile: rules::PileId,
     position: Vec2,
+    fan: rules::variant::Fan,
+    layer: usize,
     slot: components::cards::CardSlot,
+    /// The `CardSlot` entity, moved along with `position` when the window changes shape.
+    entity: Entity,
 }
 
-//will probably just switch to using components for each type of card pile, seems like a better,
-//cleaner way to do this then screwing around with whatever I was thinking about here.
-#[derive(Resource, Reflect, Clone, Copy, Debug, Default)]
+/// Where every pile of the game being played sits, built from its variant's layout and placed to
+/// fit the window.
+#[derive(Resource, Clone, Debug, Default)]
 struct CardSlotPositions {
-    stock_pile: Option<Slot>,
-    waste_pile: Option<Slot>,
-    foundations: [Option<Slot>; 4],
-    tableau: [Option<Slot>; 7],
+    slots: Vec<Slot>,
+    /// The lowest a card's centre can go and still be on screen, tableau columns squeeze up to
+    /// stay above it.
+    floor: f32,
 }
 
 impl CardSlotPositions {
-    fn new() -> CardSlotPositions {
-        CardSlotPositions {
-            stock_pile: None,
-            waste_pile: None,
-            foundations: [None, None, None, None],
-            tableau: [None, None, None, None, None, None, None],
-        }
+    fn get(&self, pile: rules::PileId) -> Option<&Slot> {
+        self.slots.iter().find(|s| s.pile == pile)
+    }
+
+    fn by_entity(&self, entity: Entity) -> Option<&Slot> {
+        self.slots.iter().find(|s| s.entity == entity)
     }
 }
 
 //const BOARD_POSITION_OFFSET: Vec2 = Vec2::new(-400.0, 310.0);
-const PADDING: f32 = 7.0;
-
-fn generate_board(mut commands: Commands) {
-    use components::cards::CARD_SIZE;
-    let mut pos = CardSlotPositions::new();
-    let card_width = CARD_SIZE.x;
-    let card_spacing = Vec2::new(card_width + PADDING, 0.0);
-    let board_width = (card_width * 7.0) + (PADDING * 6.0);
-    let p = Vec2::new(board_width / -2.0, 0.0);
-    let s = build_slot_to_spawn(p);
-    pos.stock_pile = Some(Slot {
-        position: p,
-        slot: s.1,
-    });
-    commands.spawn(s);
-    let p = p + card_spacing;
-    let s = build_slot_to_spawn(p);
-    pos.waste_pile = Some(Slot {
-        position: p,
-        slot: s.1,
-    });
-    commands.spawn(s);
-    let p = p + card_spacing;
-    let p = p + card_spacing;
-    for i in 0..4 {
-        let p = p + (card_spacing * i as f32);
-        let s = build_slot_to_spawn(p);
-        pos.foundations[i] = Some(Slot {
-            position: p,
-            slot: s.1,
-        });
-        commands.spawn(s);
-    }
-    //second row
-    let p = Vec2::new(board_width / -2.0, -(CARD_SIZE.y + PADDING));
-    for i in 0..7 {
-        let p = p + (card_spacing * i as f32);
+
+fn generate_board(
+    mut commands: Commands,
+    game: Res<systems::board::Game>,
+    mode: Res<systems::table::LayoutMode>,
+    window: Query<&Window, With<PrimaryWindow>>,
+) {
+    let layout = game.state.variant().layout();
+    // a minimised window gets something sensible until it's opened back up
+    let size = systems::table::window_size(window.single()).unwrap_or(Vec2::new(1280.0, 720.0));
+    let placement = systems::table::place(&layout, size, *mode);
+    let mut pos = CardSlotPositions {
+        floor: placement.floor,
+        ..default()
+    };
+    for (pile_slot, p) in layout.slots.into_iter().zip(placement.positions) {
         let s = build_slot_to_spawn(p);
-        pos.tableau[i] = Some(Slot {
+        let slot = s.1;
+        pos.slots.push(Slot {
+            pile: pile_slot.pile,
             position: p,
-            slot: s.1,
+            fan: pile_slot.fan,
+            layer: pile_slot.layer,
+            slot,
+            entity: commands.spawn(s).id(),
         });
-        commands.spawn(s);
     }
     commands.insert_resource(pos);
 }
 
-fn build_slot_to_spawn(pos: Vec2) -> (SpatialBundle, components::cards::CardSlot) {
+fn build_slot_to_spawn(
+    pos: Vec2,
+) -> (
+    SpatialBundle,
+    components::cards::CardSlot,
+    components::cards::PileCards,
+) {
     let slot = components::cards::CardSlot;
     (
         bevy::prelude::SpatialBundle {
@@ -186,6 +404,7 @@ fn build_slot_to_spawn(pos: Vec2) -> (SpatialBundle, components::cards::CardSlot
             ..default()
         },
         slot,
+        default(),
     )
 }
 
@@ -197,95 +416,44 @@ enum MoveState {
     RemoveComponent,
 }
 
+/// Carries a card over to its `CardTarget`, turning it face up or down on the way.
 #[derive(Component, Reflect, Clone, Copy, Debug)]
 pub struct MoveThisCard {
-    target: Option<Entity>,
     start_position: Vec2,
+    start_rotation: Quat,
     moving: MoveState,
     time_at_start_of_move: u128,
     time_to_finish_move: u128,
     rotation_freqs: (i8, i8, i8),
+    face_up: bool,
 }
 
 fn move_cards(
     mut commands: Commands,
     time: Res<Time>,
-    mut move_card: Query<(Entity, &mut Transform, &mut MoveThisCard)>,
-    mut draggables: Query<
-        (Entity, &mut Transform, &components::cards::CardDraggable),
-        Without<MoveThisCard>,
-    >,
+    mut move_card: Query<(
+        Entity,
+        &mut Transform,
+        &mut MoveThisCard,
+        &components::cards::CardTarget,
+    )>,
 ) {
-    //: Vec<(Entity, &mut Transform, &components::cards::CardDraggable)> =
-
     let top_of_the_stack = 500.0;
-    //let mut draggables = draggables.into_iter();
 
     let current_time = time.elapsed().as_millis();
-    for (ea, mut txa, mut ca) in &mut move_card.iter_mut() {
+    for (ea, mut txa, mut ca, target) in &mut move_card.iter_mut() {
         let tx = txa.as_mut();
         let c = ca.as_mut();
-        //let mut all_draggables: Vec<(Entity, &Transform)> =
-        //    draggables.into_iter().map(|x| (x.0, x.1)).collect();
-        //println!("{:?}", draggables);
-        let slot_entity_from_movethiscard = draggables
-            .iter()
-            .find(|x| x.0 == c.target.expect("fuckin in slot_from"))
-            .expect("No CardDraggable found matching this MoveThisCard target.")
-            .0;
         match c.moving {
             MoveState::StartMove => {
                 tx.translation.z = top_of_the_stack;
 
-                let mut depth_list: Vec<(Entity, f32)> = Vec::new();
-                for x in draggables.iter() {
-                    depth_list.push((
-                        x.0,
-                        if x.0 == c.target.expect("target missing") {
-                            top_of_the_stack
-                        } else {
-                            x.1.translation.z
-                        },
-                    ));
-                }
-                //eprintln!("before = {:#?}", depth_list);
-                depth_list.sort_by(|a, b| a.1.total_cmp(&b.1));
-                //eprintln!("after = {:#?}", depth_list);
-                for mut v in draggables.iter_mut() {
-                    v.1.translation.z = depth_list
-                        .iter()
-                        .enumerate()
-                        .find(|x| v.0 == x.1 .0)
-                        .expect("still not findind matching id's in draggables and depth list")
-                        .0 as f32;
-                }
-
-                // println!("{:#?}", tx);
-                start_new_move(
-                    tx,
-                    Some(slot_entity_from_movethiscard),
-                    /*
-                    Some(
-                        slot_from_movethiscard
-                            .expect("Somehow got past the early return on none")
-                            .0,
-                    ),
-                    */
-                    c,
-                    current_time,
-                );
-                /*let draggables = draggables
-                    .iter_mut()
-                    .collect()
-                    .sort_by(|a, b| a.1.translation.z.total_cmp(&b.1.translation.z));
-                fo:r (i, el) in draggables.iter_mut().enumerate() {
-                    el.1.translation.z = i as f32;
-                }
-                */
-                //sort_stack(all_draggables);
+                // depth is handled by card_depth now, sorting here put a batch of cards that
+                // started moving on the same frame in whatever order the query gave them.
+                start_new_move(tx, c, current_time);
             }
             MoveState::Moving => {
-                moving_stuff(tx, draggables.iter().collect(), c, current_time);
+                moving_stuff(tx, target.0, c, current_time);
             }
             MoveState::EndMove => {
                 c.moving = MoveState::RemoveComponent;
@@ -298,51 +466,33 @@ fn move_cards(
     }
 }
 
-fn card_stacking(
-    mut draggables: Query<(Entity, &mut Transform, &components::cards::CardDraggable)>,
-    mut card_visuals: Query<
-        (Entity, &mut Transform, &components::cards::CardVisual),
-        Without<components::cards::CardDraggable>,
+/// Draws every card at the depth its place in its pile gives it, on top of the pile's layer.
+/// Cards on their way somewhere pass over the piles they cross and held ones go over everything.
+/// The cascade sets its own.
+fn card_depth(
+    slots: Res<CardSlotPositions>,
+    dragging: Res<Dragging>,
+    mut cards: Query<
+        (
+            Entity,
+            &mut Transform,
+            &components::cards::InPile,
+            Option<&MoveThisCard>,
+        ),
+        Without<systems::win::BouncingCard>,
     >,
 ) {
-    // let mut x: Vec<(Entity, f32, Option<Entity>)> = Vec::new();
-    // for n in draggables.iter() {
-    //     x.push((n.0, n.1.translation.z, n.2.card));
-    // }
-    // x.sort_by(|a, b| a.1.total_cmp(&b.1));
-    // let mut x2 = (0..).zip(x.iter_mut());
-    // let mut cv_iter = card_visuals.iter_mut();
-    // for mut n in draggables.iter_mut() {
-    //     let temp = x2.find(|p| (p.1).0 == n.0);
-    //     if let Some(this_z) = temp {
-    //         n.1.translation.z = this_z.0 as f32;
-    //         if let Some(c) = n.2.card {
-    //             cv_iter.find(|x| x.0 == c).expect("fuck").1.translation.z = this_z.0 as f32;
-    //         }
-    //     }
-    // }
-    let mut drag: Vec<(Entity, f32, Option<Entity>)> = draggables
-        .iter_mut()
-        .map(|d| (d.0, d.1.translation.z, d.2.card))
-        .collect();
-    drag.sort_by(|a, b| a.1.total_cmp(&b.1));
-    let drag = (0..).zip(drag);
-    drag.for_each(|d| {
-        draggables
-            .iter_mut()
-            .find(|a| a.0 == d.1 .0)
-            .unwrap()
-            .1
-            .translation
-            .z = d.0 as f32;
-        card_visuals
-            .iter_mut()
-            .find(|a| a.0 == d.1 .2.unwrap())
-            .unwrap()
-            .1
-            .translation
-            .z = d.0 as f32;
-    });
+    for (e, mut tx, in_pile, moving) in cards.iter_mut() {
+        let Some(slot) = slots.by_entity(in_pile.pile) else {
+            continue;
+        };
+        let height = systems::board::card_height(&slots, slot.pile, in_pile.index);
+        tx.translation.z = match dragging.cards.iter().position(|&held| held == e) {
+            Some(k) => DRAGGING_Z + k as f32,
+            None if moving.is_some() => systems::board::TOP_OF_THE_STACK + height,
+            None => height + 1.0,
+        };
+    }
 }
 
 /*
@@ -351,52 +501,45 @@ fn sort_stack(mut stack: Vec<(Entity, &mut Transform)>) {
 }
 */
 
-fn moving_stuff(
-    tx: &mut Transform,
-    targets: Vec<(Entity, &Transform, &components::cards::CardDraggable)>,
-    c: &mut MoveThisCard,
-    t: u128,
-) {
-    let current_move_target = targets
-        .iter()
-        .find(|x| {
-            x.0 == c
-                .target
-                .expect("Got a none while trying to unwrap in Moving_stuff")
-        })
-        .expect("Got a none trying to unwrap an (&entity, &transform) in moving_stuff")
-        .1
-        .translation;
+/// Carries a card part of the way from where its move started to `target`, its depth is left to
+/// `card_depth`.
+fn moving_stuff(tx: &mut Transform, target: Vec2, c: &mut MoveThisCard, t: u128) {
     let time_since_start_of_move = (c.time_to_finish_move - c.time_at_start_of_move) as f32;
-    let percent = (t - c.time_at_start_of_move) as f32 / time_since_start_of_move;
+    // moves can be queued to start a little later, those just sit still until it's their turn
+    let percent = t.saturating_sub(c.time_at_start_of_move) as f32 / time_since_start_of_move;
     let percent_of_move_done: f32 = percent.clamp(0.0, 1.0); //if percent >= 1.0 { 1.0 } else { percent };
-    let location: Vec3 = c
-        .start_position
-        .extend(current_move_target.z)
-        .lerp(current_move_target, percent_of_move_done);
-    tx.translation = location;
+    let location = c.start_position.lerp(target, percent_of_move_done);
+    tx.translation = location.extend(tx.translation.z);
     let x = (c.rotation_freqs.0 as f32 * (percent_of_move_done * 360.0)) % 360.0;
     let y = (c.rotation_freqs.1 as f32 * (percent_of_move_done * 360.0)) % 360.0;
     let z = (c.rotation_freqs.2 as f32 * (percent_of_move_done * 360.0)) % 360.0;
+    let resting = if c.face_up {
+        Quat::IDENTITY
+    } else {
+        Quat::from_rotation_y(std::f32::consts::PI)
+    };
+    // a card changing sides turns over around Y on the way, that's the flip when a tableau card
+    // gets uncovered
+    let facing = c.start_rotation.slerp(resting, percent_of_move_done);
     tx.rotation = Quat::from_euler(
         EulerRot::XYZ,
         x.to_radians(),
         y.to_radians(),
         z.to_radians(),
-    );
+    ) * facing;
     if c.time_to_finish_move <= t {
         println!("Setting move state to end");
         c.moving = MoveState::EndMove;
     }
 }
 
-fn start_new_move(tx: &mut Transform, e: Option<Entity>, c: &mut MoveThisCard, t: u128) {
+fn start_new_move(tx: &mut Transform, c: &mut MoveThisCard, t: u128) {
     println!("current time is : {}", t);
     println!("time to finish is : {}", c.time_to_finish_move);
-    c.target = e;
     //c.time_at_start_of_move = t;
     //c.time_to_finish_move = t + r.gen_range(2000..3000);
     c.start_position = tx.translation.truncate();
+    c.start_rotation = tx.rotation;
     c.moving = MoveState::Moving;
 }
 
@@ -419,119 +562,234 @@ fn _test_system(
     }
 }
 
+/// The mouse buttons and where the cursor is, with what the last clicks did.
+#[derive(SystemParam)]
+struct Mouse<'w> {
+    clicks: Res<'w, Input<MouseButton>>,
+    pos: Res<'w, MousePosition>,
+    drag: ResMut<'w, Dragging>,
+    last_click: ResMut<'w, LastClickedEntity>,
+    /// For telling double clicks apart.
+    time: Res<'w, Time>,
+}
+
+/// The cards that can be clicked on, and the piles they're filed in.
+#[derive(SystemParam)]
+struct ClickableCards<'w, 's> {
+    cards: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Transform,
+            &'static components::cards::CardVisual,
+            &'static components::cards::CardTarget,
+        ),
+    >,
+    filed: Query<'w, 's, &'static components::cards::PileCards>,
+}
+
 fn mouse_input(
     mut commands: Commands,
-    mouse_clicks: Res<Input<MouseButton>>,
-    mut drag: ResMut<Dragging>,
-    pos: Res<MousePosition>,
-    time: Res<Time>,
-    draggables: Query<(Entity, &Transform, &components::cards::CardDraggable)>,
+    mouse: Mouse,
+    mut game: ResMut<systems::board::Game>,
+    slots: Res<CardSlotPositions>,
+    mut moves: EventWriter<systems::board::PlayMove>,
+    clickable: ClickableCards,
+    paused: Res<systems::pause::Paused>,
 ) {
+    use rules::{autoplay, Move, PileId};
+    let Mouse {
+        clicks: mouse_clicks,
+        pos,
+        mut drag,
+        mut last_click,
+        time,
+    } = mouse;
+    let ClickableCards { cards, filed } = clickable;
+    // the cards belong to the cascade now, or the clicks to the pause menu
+    if game.state.is_won() || paused.open {
+        return;
+    }
     if mouse_clicks.just_released(MouseButton::Left) {
-        if drag.card_id.is_none() {
-            return;
+        if let (Some(&grabbed), Some(from)) = (drag.cards.first(), drag.pile) {
+            let (_, card_tx, _, _) = cards
+                .get(grabbed)
+                .expect("Dragged card doesn't have a CardVisual.");
+            let dropped_on =
+                systems::board::drop_target(&game.state, &slots, card_tx.translation.truncate());
+            match dropped_on {
+                Some(to) if to != from => moves.send(systems::board::PlayMove(Move::Transfer {
+                    from,
+                    to,
+                    count: drag.cards.len(),
+                })),
+                // dropped on nothing, the board sync sends them back to where they were picked up
+                _ => game.set_changed(),
+            }
         }
-        let the_card_id = drag
-            .card_id
-            .expect("None card_id when trying to build MoveThisCard");
-        let the_card_draggable = drag
-            .card_draggable
-            .card
-            .expect("None from card draggable when trying to build MoveThisCard");
-        commands.entity(the_card_draggable).insert(MoveThisCard {
-            target: Some(the_card_id),
-            start_position: drag.card_start_position,
-            moving: MoveState::StartMove,
-            time_at_start_of_move: time.elapsed().as_millis(),
-            time_to_finish_move: time.elapsed().as_millis() + 300,
-            rotation_freqs: (0, 1, 0),
-        });
-        drag.draggable = None;
+        drag.cards.clear();
+        drag.pile = None;
     }
-    if mouse_clicks.just_pressed(MouseButton::Left) {
-        use components::cards;
-        let size = cards::CARD_SIZE;
-        //I can tell I'm messing up with the distance mesaure, its not always grabbing the center
-        //of the cards, this might be the upper left corner, but this needs to all be replaced
-        //anyway and just grab cards by the highest z card that you click on.
-        let mut distance = 0.0;
-        let mut selected: Option<(Entity, &Transform, &cards::CardDraggable)> = None;
-        for d in draggables.iter() {
-            let card_rect = Rect::from_center_size(d.1.translation.truncate(), size);
-            if !card_rect.contains(pos.0) {
-                continue;
-            }
-            let this_card_distance = pos.0.distance(d.1.translation.truncate());
-            if this_card_distance < distance {
-                continue;
-            }
-            distance = this_card_distance;
-            selected = Some(d);
+    let on_stock = slots.get(PileId::Stock).is_some_and(|stock| {
+        Rect::from_center_size(stock.position, components::cards::CARD_SIZE).contains(pos.0)
+    });
+    if mouse_clicks.just_pressed(MouseButton::Left) && on_stock {
+        // a stock with nothing left to give just ignores the click
+        if let Some(mv) = game.state.stock_move() {
+            moves.send(systems::board::PlayMove(mv));
         }
-        if let Some((x, tx, cd)) = selected {
-            //println!("Card selected: {:?}", selected);
-            dbg!(selected);
-            drag.draggable = Some(x);
-            let current_card_pos = tx.translation.truncate();
-            drag.offset = pos.0 - current_card_pos;
-            drag.card_start_position = current_card_pos;
-            drag.card_id = Some(x);
-            drag.card_draggable = *cd;
+    } else if mouse_clicks.any_just_pressed([MouseButton::Left, MouseButton::Right]) {
+        // grab whichever card is drawn on top under the cursor, going by where the cards belong
+        // so one still on its way can be picked up where it's going
+        let selected = cards
+            .iter()
+            .filter(|(_, _, _, target)| {
+                Rect::from_center_size(target.0, components::cards::CARD_SIZE).contains(pos.0)
+            })
+            .max_by(|a, b| a.1.translation.z.total_cmp(&b.1.translation.z));
+        if let Some((x, _, visual, target)) = selected {
+            if let Some((pile, i)) = game.state.find(visual.id()) {
+                let pile_cards = game.state.pile(pile).expect("find gave back a real pile");
+                let count = pile_cards.len() - i;
+                let now = time.elapsed_seconds();
+                let double_click = matches!(
+                    last_click.0,
+                    Some((last, at)) if last == x && now - at < DOUBLE_CLICK_SECS
+                );
+                let auto_move = double_click || mouse_clicks.just_pressed(MouseButton::Right);
+                let pickable = pile != PileId::Stock && game.state.can_pick_up(pile, count);
+                if pickable && auto_move {
+                    last_click.0 = None;
+                    if let Some(mv) = autoplay::best_destination(&game.state, pile, count) {
+                        moves.send(systems::board::PlayMove(mv));
+                    }
+                } else if pickable {
+                    last_click.0 = Some((x, now));
+                    // the pile's own list of its cards, filed by the board sync
+                    let Some(in_pile) = slots
+                        .get(pile)
+                        .and_then(|slot| filed.get(slot.entity).ok())
+                        .filter(|f| f.0.len() == pile_cards.len())
+                    else {
+                        return;
+                    };
+                    drag.cards = in_pile.0[i..].to_vec();
+                    drag.offset = pos.0 - target.0;
+                    drag.pile = Some(pile);
+                    for &held in drag.cards.iter() {
+                        commands.entity(held).remove::<MoveThisCard>();
+                    }
+                }
+            }
         }
         println!("Mouse clicked at {}, {}", pos.0.x, pos.0.y);
     }
 }
 
+/// Undo, redo, hints and pausing. Playing from the keyboard is `systems::focus`.
 fn keyboard_input(
-    mut commands: Commands,
     keys: Res<Input<KeyCode>>,
-    test: Res<CardSlotPositions>,
-    current: Res<components::cards::CurrentCard>,
-    mut cards: Query<(&mut Transform, &mut components::cards::CardVisual)>,
-    gizmos_toggle: Res<inspector::GizmosDraw>,
+    mut undo: EventWriter<systems::board::Undo>,
+    mut redo: EventWriter<systems::board::Redo>,
+    mut hint: EventWriter<systems::hint::ShowHint>,
+    paused: Res<systems::pause::Paused>,
+    mut pause: EventWriter<systems::pause::TogglePause>,
 ) {
-    let the_cards = &mut cards;
-    let it = &mut the_cards.iter_mut();
-    let mut index: usize = current.0;
-    if keys.just_pressed(KeyCode::Space) {
-        println!("{:?}", it.nth(index));
+    if keys.just_pressed(KeyCode::P) {
+        pause.send(systems::pause::TogglePause);
+    }
+    // the game's on hold while the menu's open
+    if paused.open {
+        return;
     }
-    if keys.just_pressed(KeyCode::Up) {
-        print!("before up {} , ", index);
-        index += 1;
-        println!("after up {}", index);
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if ctrl && keys.just_pressed(KeyCode::Z) {
+        undo.send(systems::board::Undo);
     }
-    if keys.just_pressed(KeyCode::Down) {
-        print!("before up {} , ", index);
-        index -= 1;
-        println!("after up {}", index);
+    if ctrl && keys.just_pressed(KeyCode::Y) {
+        redo.send(systems::board::Redo);
     }
-    if keys.just_pressed(KeyCode::Right) {
-        let t = it.nth(index);
-        let temp = t.expect("shit");
-        let (mut temp2, _) = temp;
-        println!("{:#?}", temp2);
-        temp2.translation = temp2.translation + temp2.local_x() * 25.0;
+    if keys.just_pressed(KeyCode::H) {
+        hint.send(systems::hint::ShowHint { back: shift });
     }
+}
+
+/// The debugging keys, they still work while the game's paused.
+fn debug_keys(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    test: Res<CardSlotPositions>,
+    gizmos_toggle: Res<inspector::GizmosDraw>,
+) {
     if keys.just_pressed(KeyCode::G) {
         println!("{:#?}", test);
     }
     if keys.just_pressed(KeyCode::D) {
         commands.insert_resource(inspector::GizmosDraw(!gizmos_toggle.0));
     }
-    commands.insert_resource(components::cards::CurrentCard(index));
+}
+
+/// Picks up the saved game unless the command line asked for something in particular.
+fn start_game(
+    mut commands: Commands,
+    deal: Res<StartingDeal>,
+    mut dealt: EventWriter<systems::board::GameDealt>,
+) {
+    let restored = match (deal.deal, &deal.variant, deal.rules_given) {
+        (None, None, false) => systems::save::restore(),
+        _ => None,
+    };
+    let restored = restored.and_then(|(game, clock, scoring)| match deal.scoring {
+        Some(asked) if asked != scoring => {
+            info!("Dealing afresh, the saved game is scored {:?}", scoring);
+            None
+        }
+        _ => {
+            commands.insert_resource(scoring);
+            Some((game, clock))
+        }
+    });
+    let (game, clock) = restored.unwrap_or_else(|| {
+        let variant = deal.new_variant();
+        let seed = match deal.deal {
+            Some(seed) => seed,
+            // something to put on the table while a winnable one is looked for, the one that's
+            // found gets dealt like any new game
+            None if systems::board::needs_winnable_search(&variant, deal.winnable_only) => {
+                commands.insert_resource(systems::board::Dealing::start(variant.clone()));
+                return (
+                    systems::board::Game::new(systems::board::random_deal(&variant), &variant),
+                    default(),
+                );
+            }
+            None => systems::board::random_deal(&variant),
+        };
+        // a fresh deal gets paid for and saved like any other, a restored game already was
+        dealt.send(systems::board::GameDealt);
+        (systems::board::Game::new(seed, &variant), default())
+    });
+    info!("Playing deal {}", game.deal);
+    commands.insert_resource(game);
+    commands.insert_resource(clock);
 }
 
 fn setup(
     mut commands: Commands,
-    time: Res<Time>,
+    slots: Res<CardSlotPositions>,
+    game: Res<systems::board::Game>,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
 ) {
     commands.spawn(Camera2dBundle::default());
-    let rng = &mut rand::thread_rng();
-    for i in 0..52 {
-        use components::cards::{self, CardColor, CardSuit, CardVisual, CARD_SIZE};
+    // everything starts face down on the stock, or the middle of the table for games without
+    // one, and the board sync deals it out from there
+    let stock = slots
+        .get(rules::PileId::Stock)
+        .map_or(Vec2::ZERO, |stock| stock.position);
+    for c in game.state.cards() {
+        use components::cards::{self, CARD_SIZE};
         let texture_handle = asset_server.load("cards.png");
         let texture_atlas = TextureAtlas::from_grid(
             texture_handle,
@@ -542,60 +800,28 @@ fn setup(
             None,
         );
         let texture_atlas_handle = texture_atlases.add(texture_atlas);
-        let suit;
-        let color;
-        match i {
-            (0..=12) => {
-                suit = CardSuit::Hearts;
-                color = CardColor::Red;
-            }
-            (13..=25) => {
-                suit = CardSuit::Spades;
-                color = CardColor::Black;
-            }
-            (26..=39) => {
-                suit = CardSuit::Diamonds;
-                color = CardColor::Red;
-            }
-            (40..=52) => {
-                suit = CardSuit::Clubs;
-                color = CardColor::Black;
-            }
-            _ => {
-                suit = CardSuit::Hearts;
-                color = CardColor::Black;
-                println!("got out of range when generating cards")
-            }
-        }
-        let c = CardVisual {
-            index: i + 1,
-            number: (i % 13) + 1,
-            suit,
-            color,
-        };
-        let initial_position = Vec3::new(
-            rng.gen_range(-300.0..=300.0),
-            rng.gen_range(-300.0..=300.0),
-            rng.gen_range(-300.0..=0.0),
-        );
 
-        let ent = commands
+        commands
             .spawn((
                 SpatialBundle {
                     transform: Transform {
-                        translation: Vec3::ZERO, //initial_position,
+                        translation: stock.extend(0.0),
+                        rotation: Quat::from_rotation_y(std::f32::consts::PI),
                         ..default()
                     },
                     ..default()
                 },
-                c,
+                cards::CardVisual(c),
+                cards::CardTarget(stock),
+                inspector::DebugRect,
+                Clickable,
             ))
             .with_children(|parent| {
                 parent.spawn((
                     SpriteSheetBundle {
                         texture_atlas: texture_atlas_handle,
                         sprite: TextureAtlasSprite {
-                            index: i,
+                            index: c.index - 1,
                             ..default()
                         },
                         ..default()
@@ -616,29 +842,6 @@ fn setup(
                     },
                     cards::CardBack,
                 ));
-            })
-            .id();
-        let card_drag = commands
-            .spawn((
-                SpatialBundle {
-                    transform: Transform {
-                        translation: initial_position,
-                        ..default()
-                    },
-                    ..default()
-                },
-                inspector::DebugRect,
-                cards::CardDraggable { card: Some(ent) },
-                Clickable,
-            ))
-            .id();
-        commands.entity(ent).insert(MoveThisCard {
-            target: Some(card_drag),
-            start_position: initial_position.truncate(),
-            moving: MoveState::StartMove,
-            time_at_start_of_move: time.elapsed().as_millis(),
-            time_to_finish_move: time.elapsed().as_millis() + 1000,
-            rotation_freqs: (rng.gen_range(-1..=1), rng.gen_range(-1..=1), 0),
-        });
+            });
     }
 }
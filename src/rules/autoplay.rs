@@ -0,0 +1,342 @@
+//! Picking moves for the player, for the clicks that mean "put this wherever it goes" and for
+//! hints.
+
+use super::variant::VariantState;
+use super::{Move, PileId, Rules};
+
+/// Where the top `count` cards of `from` get sent when they're double or right clicked.
+/// Foundations come first, then the waste or another card out in the open for the games that
+/// play onto those, then a tableau column that already has cards, then an empty column, and a
+/// free cell last.
+pub fn best_destination(game: &VariantState, from: PileId, count: usize) -> Option<Move> {
+    let is_empty = |p: &PileId| game.pile(*p).map_or(0, |c| c.len()) == 0;
+    let piles = game.piles();
+    let foundations = piles.iter().filter(|p| matches!(p, PileId::Foundation(_)));
+    let played_on = piles
+        .iter()
+        .filter(|p| matches!(p, PileId::Waste | PileId::Spot(_)));
+    let tableau = piles.iter().filter(|p| matches!(p, PileId::Tableau(_)));
+    let built_on = tableau.clone().filter(|p| !is_empty(p));
+    // a whole column moving into an empty one gets nowhere
+    let whole_column =
+        matches!(from, PileId::Tableau(_)) && game.pile(from).is_some_and(|c| c.len() == count);
+    let empty = tableau.filter(|p| is_empty(p) && !whole_column);
+    let cells = piles
+        .iter()
+        .filter(|p| matches!(p, PileId::FreeCell(_)) && !matches!(from, PileId::FreeCell(_)));
+    foundations
+        .chain(played_on)
+        .chain(built_on)
+        .chain(empty)
+        .chain(cells)
+        .map(|&to| Move::Transfer { from, to, count })
+        .find(|mv| game.check(*mv).is_ok())
+}
+
+/// Nothing left can go wrong once the stock and waste are empty and every tableau card is face
+/// up, each column is already a built run so the cards just need to be put away. Only Klondike
+/// works like that.
+pub fn can_auto_complete(game: &VariantState) -> bool {
+    let VariantState::Klondike(game) = game else {
+        return false;
+    };
+    game.stock.is_empty()
+        && game.waste.is_empty()
+        && game.tableau.iter().flatten().all(|c| c.face_up)
+        && !game.is_won()
+}
+
+/// The foundation moves that finish a game `can_auto_complete` says is done, lowest cards first
+/// so it looks like someone dealing them off.
+pub fn auto_complete(game: &VariantState) -> Vec<Move> {
+    let mut game = game.clone();
+    let piles = game.piles();
+    let tableau = piles.iter().filter(|p| matches!(p, PileId::Tableau(_)));
+    let foundations = piles.iter().filter(|p| matches!(p, PileId::Foundation(_)));
+    let mut moves = Vec::new();
+    loop {
+        let next = tableau
+            .clone()
+            .filter_map(|&from| {
+                let top = game.pile(from)?.last()?.card.number;
+                foundations
+                    .clone()
+                    .map(|&to| Move::Transfer { from, to, count: 1 })
+                    .find(|mv| game.check(*mv).is_ok())
+                    .map(|mv| (top, mv))
+            })
+            .min_by_key(|(rank, _)| *rank);
+        let Some((_, mv)) = next else {
+            break;
+        };
+        game.apply(mv).expect("just checked");
+        moves.push(mv);
+    }
+    moves
+}
+
+/// Moves worth suggesting, best first: cards going up to the foundations, then moves that turn a
+/// face down card over, then ones that empty a column or leave a card on top that can go
+/// somewhere, then playing off the waste or out of a free cell, with turning the stock over and
+/// parking a card in a free cell after that. Moves that only shuffle cards around come last, so
+/// an empty list means there's nothing left to do.
+pub fn hints(game: &VariantState) -> Vec<Move> {
+    let is_empty = |p: PileId| game.pile(p).map_or(0, |c| c.len()) == 0;
+    let piles = game.piles();
+    let first_empty =
+        |kind: fn(&PileId) -> bool| piles.iter().copied().find(|&p| kind(&p) && is_empty(p));
+    let first_empty_column = first_empty(|p| matches!(p, PileId::Tableau(_)));
+    let first_empty_foundation = first_empty(|p| matches!(p, PileId::Foundation(_)));
+    let first_empty_cell = first_empty(|p| matches!(p, PileId::FreeCell(_)));
+    let mut ranked: Vec<(u32, Move)> = game
+        .legal_moves()
+        .into_iter()
+        // every empty pile is as good as the next
+        .filter(|mv| match *mv {
+            Move::Transfer {
+                to: to @ PileId::Tableau(_),
+                ..
+            } => !is_empty(to) || first_empty_column == Some(to),
+            Move::Transfer {
+                to: to @ PileId::Foundation(_),
+                ..
+            } => !is_empty(to) || first_empty_foundation == Some(to),
+            Move::Transfer {
+                to: to @ PileId::FreeCell(_),
+                ..
+            } => first_empty_cell == Some(to),
+            // a pair comes off the same whichever card goes on the other
+            Move::Transfer {
+                from: PileId::Spot(a),
+                to: PileId::Spot(b),
+                ..
+            } => a < b,
+            Move::Transfer {
+                from: PileId::Waste,
+                to: PileId::Spot(_),
+                ..
+            } => false,
+            _ => true,
+        })
+        .map(|mv| (hint_rank(game, mv), mv))
+        .collect();
+    ranked.sort_by_key(|(rank, _)| std::cmp::Reverse(*rank));
+    ranked.into_iter().map(|(_, mv)| mv).collect()
+}
+
+/// How good a hint `mv` makes, 0 for moves that only shuffle cards around.
+fn hint_rank(game: &VariantState, mv: Move) -> u32 {
+    let Move::Transfer { from, to, count } = mv else {
+        return 1;
+    };
+    let Some(source) = game.pile(from) else {
+        return 0;
+    };
+    let left = source.len() - count;
+    let uncovers = left > 0 && !source[left - 1].face_up;
+    match (from, to) {
+        (PileId::Foundation(_), _) => 0,
+        (_, PileId::Foundation(_)) => {
+            if uncovers {
+                6
+            } else {
+                5
+            }
+        }
+        (PileId::Tableau(_), PileId::Tableau(_)) => {
+            let empties = left == 0 && game.pile(to).is_some_and(|p| !p.is_empty());
+            if uncovers {
+                4
+            } else if empties || frees_a_card(game, mv) {
+                3
+            } else {
+                0
+            }
+        }
+        (PileId::Waste | PileId::FreeCell(_), PileId::Tableau(_)) => 2,
+        (PileId::Tableau(_), PileId::FreeCell(_)) => {
+            if frees_a_card(game, mv) {
+                2
+            } else {
+                1
+            }
+        }
+        // playing off an overlapping layout, onto the waste or as a pair
+        (_, PileId::Waste | PileId::Spot(_)) => {
+            if turns_up(game, mv) {
+                4
+            } else {
+                3
+            }
+        }
+        _ => 0,
+    }
+}
+
+/// Playing `mv` leaves a card on top of where it came from that can go up to a foundation or
+/// onto a column with cards in it.
+fn frees_a_card(game: &VariantState, mv: Move) -> bool {
+    let Move::Transfer { from, .. } = mv else {
+        return false;
+    };
+    let mut after = game.clone();
+    if after.apply(mv).is_err() {
+        return false;
+    }
+    after
+        .piles()
+        .into_iter()
+        .filter(|&to| match to {
+            PileId::Foundation(_) => true,
+            PileId::Tableau(_) => after.pile(to).is_some_and(|p| !p.is_empty()),
+            _ => false,
+        })
+        .any(|to| after.check(Move::Transfer { from, to, count: 1 }).is_ok())
+}
+
+/// Playing `mv` turns a face down card over.
+fn turns_up(game: &VariantState, mv: Move) -> bool {
+    let mut after = game.clone();
+    after.apply(mv).is_ok_and(|applied| applied.flipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::rules::klondike::StockRules;
+    use crate::rules::overlap;
+    use crate::rules::shuffle::DealSeed;
+    use crate::rules::test_cards::{down, freecell_table, klondike_table, up};
+    use crate::rules::variant::Variant;
+    use crate::rules::CardSuit;
+
+    fn transfer(from: PileId, to: PileId) -> Option<Move> {
+        Some(Move::Transfer { from, to, count: 1 })
+    }
+
+    #[test]
+    fn foundations_come_before_the_tableau() {
+        let mut game = klondike_table(StockRules::default());
+        game.foundations[2] = vec![up(1, CardSuit::Hearts)];
+        game.tableau[0] = vec![up(3, CardSuit::Clubs)];
+        game.waste = vec![up(2, CardSuit::Hearts)];
+        let game = VariantState::Klondike(game);
+        assert_eq!(
+            best_destination(&game, PileId::Waste, 1),
+            transfer(PileId::Waste, PileId::Foundation(2))
+        );
+    }
+
+    #[test]
+    fn a_column_with_cards_comes_before_an_empty_one() {
+        let mut game = freecell_table();
+        game.tableau[0] = vec![up(5, CardSuit::Spades), up(7, CardSuit::Hearts)];
+        game.tableau[3] = vec![up(8, CardSuit::Clubs)];
+        game.tableau[4] = vec![up(2, CardSuit::Spades), up(10, CardSuit::Diamonds)];
+        // columns 1, 2 and 5 to 7 are empty, and come before column 3 going by the order alone
+        let game = VariantState::FreeCell(game);
+        assert_eq!(
+            best_destination(&game, PileId::Tableau(0), 1),
+            transfer(PileId::Tableau(0), PileId::Tableau(3))
+        );
+        // nothing to build on, so the first empty column
+        assert_eq!(
+            best_destination(&game, PileId::Tableau(4), 1),
+            transfer(PileId::Tableau(4), PileId::Tableau(1))
+        );
+    }
+
+    #[test]
+    fn a_whole_column_goes_to_a_cell_rather_than_an_empty_column() {
+        let mut game = freecell_table();
+        game.tableau[0] = vec![up(10, CardSuit::Diamonds)];
+        game.tableau[1] = vec![up(2, CardSuit::Spades)];
+        let game = VariantState::FreeCell(game);
+        assert_eq!(
+            best_destination(&game, PileId::Tableau(0), 1),
+            transfer(PileId::Tableau(0), PileId::FreeCell(0))
+        );
+    }
+
+    #[test]
+    fn auto_complete_waits_for_every_card_to_be_face_up() {
+        let mut game = klondike_table(StockRules::default());
+        for (column, suit) in [CardSuit::Hearts, CardSuit::Spades].into_iter().enumerate() {
+            game.tableau[column] = (1..=13).rev().map(|n| up(n, suit)).collect();
+        }
+        game.tableau[2] = vec![down(13, CardSuit::Clubs)];
+        game.tableau[2].extend((1..=12).rev().map(|n| up(n, CardSuit::Clubs)));
+        game.tableau[3] = (1..=13).rev().map(|n| up(n, CardSuit::Diamonds)).collect();
+        assert!(!can_auto_complete(&VariantState::Klondike(game.clone())));
+
+        game.tableau[2][0].face_up = true;
+        let mut state = VariantState::Klondike(game.clone());
+        assert!(can_auto_complete(&state));
+        let moves = auto_complete(&state);
+        assert_eq!(moves.len(), 52);
+        for mv in moves {
+            state.apply(mv).unwrap();
+        }
+        assert!(state.is_won());
+        assert!(!can_auto_complete(&state));
+    }
+
+    #[test]
+    fn auto_complete_waits_for_the_stock_and_waste() {
+        let mut game = klondike_table(StockRules::default());
+        game.tableau[0] = vec![up(2, CardSuit::Hearts)];
+        game.waste = vec![up(1, CardSuit::Hearts)];
+        assert!(!can_auto_complete(&VariantState::Klondike(game.clone())));
+        game.waste.clear();
+        game.stock = vec![down(1, CardSuit::Hearts)];
+        assert!(!can_auto_complete(&VariantState::Klondike(game)));
+    }
+
+    #[test]
+    fn only_klondike_auto_completes() {
+        let mut game = freecell_table();
+        game.tableau[0] = vec![up(1, CardSuit::Hearts)];
+        assert!(!can_auto_complete(&VariantState::FreeCell(game)));
+    }
+
+    #[test]
+    fn freecell_openings_have_hints() {
+        for n in [1, 9, 13, 19] {
+            let game = Variant::FreeCell.deal(DealSeed::Number(n));
+            assert!(!hints(&game).is_empty(), "#{}", n);
+        }
+    }
+
+    #[test]
+    fn hints_whenever_there_is_a_move() {
+        let variants = [
+            Variant::Klondike(StockRules::default()),
+            Variant::FreeCell,
+            Variant::Spider { suits: 1 },
+            Variant::Overlap(overlap::Rules::Pyramid),
+            Variant::Overlap(overlap::Rules::TriPeaks),
+        ];
+        for variant in variants {
+            for seed in 0..10 {
+                let mut game = variant.deal(DealSeed::Seed(seed));
+                let mut rng = StdRng::seed_from_u64(seed);
+                for _ in 0..200 {
+                    let moves = game.legal_moves();
+                    assert_eq!(
+                        hints(&game).is_empty(),
+                        moves.is_empty(),
+                        "{:?} seed {}",
+                        variant,
+                        seed
+                    );
+                    if moves.is_empty() {
+                        break;
+                    }
+                    game.apply(moves[rng.gen_range(0..moves.len())]).unwrap();
+                }
+            }
+        }
+    }
+}
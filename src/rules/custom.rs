@@ -0,0 +1,820 @@
+//! Variants described in a RON file instead of in Rust, so a new game can be tried out without a
+//! rebuild. A `Definition` lays out groups of piles, says how many cards each gets dealt, how
+//! cards build on the tableau and foundations, what the stock does and when the game is won.
+//! `load` checks all of that up front and says exactly what's wrong with a file, so nothing
+//! further in has to cope with a definition that doesn't make sense.
+//!
+//! Klondike written out this way looks like:
+//!
+//! ```ron
+//! (
+//!     name: "Klondike",
+//!     piles: [
+//!         (kind: Stock, at: (0, 0)),
+//!         (kind: Waste, at: (1, 0), fan: Right(showing: 1)),
+//!         (kind: Foundation, at: (3, 0), count: 4),
+//!         (kind: Tableau, at: (0, 1), count: 7, fan: Down,
+//!          deal: [(0, 1), (1, 1), (2, 1), (3, 1), (4, 1), (5, 1), (6, 1)]),
+//!     ],
+//!     stock: (draw: 1),
+//!     tableau: (direction: Down, suits: Alternate, empty: Rank(13), moves: Runs),
+//!     win: AllOnFoundations,
+//! )
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::variant::{Fan, Layout, PileSlot};
+use super::{Applied, Card, IllegalMove, Move, PileCard, PileId, Rules};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Definition {
+    pub name: String,
+    #[serde(default = "one")]
+    pub decks: usize,
+    pub piles: Vec<PileGroup>,
+    #[serde(default)]
+    pub stock: StockRule,
+    pub tableau: BuildRule,
+    #[serde(default = "BuildRule::foundation")]
+    pub foundation: BuildRule,
+    pub win: Win,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PileKind {
+    Stock,
+    Waste,
+    Foundation,
+    Tableau,
+    /// Holds a single card, like FreeCell's.
+    Cell,
+}
+
+/// A row or column of piles of one kind.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PileGroup {
+    pub kind: PileKind,
+    /// Where the first pile goes, in card widths and heights with `PADDING` between them, from
+    /// the top left.
+    pub at: (f32, f32),
+    #[serde(default = "one")]
+    pub count: usize,
+    /// From one pile of the group to the next, one card to the right unless it says otherwise.
+    #[serde(default = "one_right")]
+    pub step: (f32, f32),
+    #[serde(default)]
+    pub fan: Fan,
+    /// How many cards each pile is dealt, face down then face up. Either one entry for every
+    /// pile or a single one they all get. The stock gets whatever's left instead.
+    #[serde(default)]
+    pub deal: Vec<(usize, usize)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StockRule {
+    #[serde(default = "one")]
+    pub draw: usize,
+    /// Times through the stock, no limit when it's left out.
+    #[serde(default)]
+    pub passes: Option<usize>,
+    #[serde(default)]
+    pub to: StockTarget,
+}
+
+impl Default for StockRule {
+    fn default() -> StockRule {
+        StockRule {
+            draw: 1,
+            passes: None,
+            to: StockTarget::Waste,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StockTarget {
+    /// `draw` cards at a time onto the waste.
+    #[default]
+    Waste,
+    /// One card onto every tableau pile, the way Spider deals.
+    Tableau,
+}
+
+/// What can go on what in one kind of pile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuildRule {
+    pub direction: Direction,
+    #[serde(default)]
+    pub suits: Suits,
+    /// Kings and Aces go on each other.
+    #[serde(default)]
+    pub wrap: bool,
+    /// What can go into the pile once it's empty.
+    #[serde(default)]
+    pub empty: Empty,
+    /// What can be picked up off the pile together. Only means anything for the tableau.
+    #[serde(default)]
+    pub moves: Moves,
+}
+
+impl BuildRule {
+    /// Up in suit from the Ace.
+    fn foundation() -> BuildRule {
+        BuildRule {
+            direction: Direction::Up,
+            suits: Suits::Same,
+            wrap: false,
+            empty: Empty::Rank(1),
+            moves: Moves::One,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    Down,
+    Up,
+    Either,
+    /// Nothing builds, only empty piles take cards.
+    Neither,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Suits {
+    #[default]
+    Any,
+    Alternate,
+    SameColor,
+    Same,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Empty {
+    #[default]
+    Any,
+    /// Only this rank, 1 for Aces up to 13 for Kings.
+    Rank(usize),
+    Nothing,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Moves {
+    /// Just the top card.
+    #[default]
+    One,
+    /// Cards built on each other by the rule.
+    Runs,
+    /// Any face up cards together, built or not.
+    AnyFaceUp,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Win {
+    /// Every card up on the foundations.
+    AllOnFoundations,
+    /// The tableau and cells cleared, whatever's left in the stock.
+    TableauCleared,
+}
+
+fn one() -> usize {
+    1
+}
+
+fn one_right() -> (f32, f32) {
+    (1.0, 0.0)
+}
+
+#[derive(Debug)]
+pub enum DefinitionError {
+    Io(io::Error),
+    /// Not RON, or not the shape of a `Definition`. The error knows where in the file.
+    Parse(ron::error::SpannedError),
+    /// Reads fine but can't be played, which group and why.
+    Invalid(String),
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DefinitionError::Io(e) => write!(f, "couldn't read the variant file: {}", e),
+            DefinitionError::Parse(e) => write!(f, "the variant file doesn't parse, {}", e),
+            DefinitionError::Invalid(e) => write!(f, "the variant file can't be played, {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for DefinitionError {
+    fn from(e: io::Error) -> DefinitionError {
+        DefinitionError::Io(e)
+    }
+}
+
+pub fn load(path: &Path) -> Result<Definition, DefinitionError> {
+    parse(&fs::read_to_string(path)?)
+}
+
+pub fn parse(text: &str) -> Result<Definition, DefinitionError> {
+    let definition: Definition = ron::from_str(text).map_err(DefinitionError::Parse)?;
+    definition.validate().map_err(DefinitionError::Invalid)?;
+    Ok(definition)
+}
+
+impl Definition {
+    fn validate(&self) -> Result<(), String> {
+        if !(1..=8).contains(&self.decks) {
+            return Err(format!("decks is {}, it has to be 1 to 8", self.decks));
+        }
+        if self.piles.is_empty() {
+            return Err("there are no piles".to_string());
+        }
+        let count = |kind| {
+            self.piles
+                .iter()
+                .filter(|g| g.kind == kind)
+                .map(|g| g.count)
+                .sum::<usize>()
+        };
+        let mut dealt = 0;
+        for (i, group) in self.piles.iter().enumerate() {
+            let name = format!("piles[{}] ({:?})", i, group.kind);
+            if group.count == 0 {
+                return Err(format!("{} has a count of 0", name));
+            }
+            // piles run right and down from the top left, there's no table to put them on
+            // anywhere else
+            let (x, y) = group.at;
+            if !(x.is_finite() && y.is_finite() && x >= 0.0 && y >= 0.0) {
+                return Err(format!(
+                    "{} is at ({}, {}), it can't be negative",
+                    name, x, y
+                ));
+            }
+            let (dx, dy) = group.step;
+            if !(dx.is_finite() && dy.is_finite() && dx >= 0.0 && dy >= 0.0) {
+                return Err(format!(
+                    "{} has a step of ({}, {}), it can't be negative",
+                    name, dx, dy
+                ));
+            }
+            match group.kind {
+                PileKind::Stock | PileKind::Waste if group.count != 1 => {
+                    return Err(format!(
+                        "{} has a count of {}, there's only ever one",
+                        name, group.count
+                    ));
+                }
+                PileKind::Stock if !group.deal.is_empty() => {
+                    return Err(format!(
+                        "{} has a deal, the stock gets whatever's left",
+                        name
+                    ));
+                }
+                _ => {}
+            }
+            if group.deal.len() > 1 && group.deal.len() != group.count {
+                return Err(format!(
+                    "{} deals to {} piles but has {}, give one entry for each or a single one \
+                     for all",
+                    name,
+                    group.deal.len(),
+                    group.count
+                ));
+            }
+            if group.kind == PileKind::Cell && group.deal.iter().any(|&(down, up)| down + up > 1) {
+                return Err(format!("{} deals more than one card into a cell", name));
+            }
+            dealt += match group.deal.as_slice() {
+                [] => 0,
+                [(down, up)] => (down + up) * group.count,
+                deal => deal.iter().map(|(down, up)| down + up).sum(),
+            };
+        }
+        let cards = self.decks * 52;
+        if dealt > cards {
+            return Err(format!(
+                "the deal needs {} cards but {} deck(s) only have {}",
+                dealt, self.decks, cards
+            ));
+        }
+        for kind in [PileKind::Stock, PileKind::Waste] {
+            if count(kind) > 1 {
+                return Err(format!("there's more than one {:?}", kind));
+            }
+        }
+        let stock = count(PileKind::Stock) == 1;
+        if !stock && dealt < cards {
+            return Err(format!(
+                "{} cards are left after the deal and there's no Stock to put them in",
+                cards - dealt
+            ));
+        }
+        if stock && self.stock.to == StockTarget::Waste && count(PileKind::Waste) == 0 {
+            return Err("the stock draws onto the waste but there's no Waste".to_string());
+        }
+        if stock && self.stock.to == StockTarget::Tableau && count(PileKind::Tableau) == 0 {
+            return Err("the stock deals onto the tableau but there's no Tableau".to_string());
+        }
+        if self.stock.draw == 0 {
+            return Err("stock.draw is 0".to_string());
+        }
+        if self.stock.passes == Some(0) {
+            return Err("stock.passes is 0, leave it out for no limit".to_string());
+        }
+        if self.win == Win::AllOnFoundations && count(PileKind::Foundation) == 0 {
+            return Err("the win is AllOnFoundations but there's no Foundation".to_string());
+        }
+        for (name, rule) in [("tableau", &self.tableau), ("foundation", &self.foundation)] {
+            if let Empty::Rank(rank) = rule.empty {
+                if !(1..=13).contains(&rank) {
+                    return Err(format!(
+                        "{}.empty is Rank({}), ranks go from 1 to 13",
+                        name, rank
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Every pile in the order the groups list them, numbered within their kind.
+    pub fn pile_ids(&self) -> Vec<PileId> {
+        let mut next = [0; 5];
+        let mut ids = Vec::new();
+        for group in self.piles.iter() {
+            for _ in 0..group.count {
+                let n = &mut next[group.kind as usize];
+                ids.push(match group.kind {
+                    PileKind::Stock => PileId::Stock,
+                    PileKind::Waste => PileId::Waste,
+                    PileKind::Foundation => PileId::Foundation(*n),
+                    PileKind::Tableau => PileId::Tableau(*n),
+                    PileKind::Cell => PileId::FreeCell(*n),
+                });
+                *n += 1;
+            }
+        }
+        ids
+    }
+
+    pub fn layout(&self) -> Layout {
+        let ids = self.pile_ids();
+        let slots = self
+            .piles
+            .iter()
+            .flat_map(|group| {
+                (0..group.count).map(move |k| {
+                    let (column, row) = group.at;
+                    let (dx, dy) = group.step;
+                    (column + dx * k as f32, row + dy * k as f32, group.fan)
+                })
+            })
+            .zip(ids)
+            .map(|((column, row, fan), id)| PileSlot::new(id, column, row, fan))
+            .collect();
+        Layout { slots }
+    }
+
+    /// `decks` copies of the deck, each told apart with `Card::in_deck`.
+    pub fn deck(&self) -> Vec<Card> {
+        (0..self.decks)
+            .flat_map(|copy| super::new_deck().into_iter().map(move |c| c.in_deck(copy)))
+            .collect()
+    }
+}
+
+/// A game of whatever a `Definition` describes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SavedGame")]
+pub struct GameState {
+    /// Kept with the game so a save carries the rules it was played by.
+    pub definition: Arc<Definition>,
+    /// A pile for each of `Definition::pile_ids`, in that order.
+    pub piles: Vec<Vec<PileCard>>,
+    pub recycles: usize,
+    /// `Definition::pile_ids`, worked out once rather than on every pile lookup. Not saved, a
+    /// restored game works them out again from its definition.
+    #[serde(skip_serializing)]
+    pile_ids: Vec<PileId>,
+}
+
+/// What a save keeps of a `GameState`.
+#[derive(Deserialize)]
+struct SavedGame {
+    definition: Arc<Definition>,
+    piles: Vec<Vec<PileCard>>,
+    recycles: usize,
+}
+
+impl From<SavedGame> for GameState {
+    fn from(saved: SavedGame) -> GameState {
+        GameState {
+            pile_ids: saved.definition.pile_ids(),
+            definition: saved.definition,
+            piles: saved.piles,
+            recycles: saved.recycles,
+        }
+    }
+}
+
+impl GameState {
+    /// Deals each group's piles in turn, the face down cards first, and leaves the rest in the
+    /// stock.
+    pub fn deal(definition: Arc<Definition>, deck: Vec<Card>) -> GameState {
+        let mut deck = deck.into_iter();
+        let mut piles = Vec::new();
+        for group in definition.piles.iter() {
+            let counts: Vec<(usize, usize)> = (0..group.count)
+                .map(|k| match group.deal.as_slice() {
+                    [] => (0, 0),
+                    [all] => *all,
+                    each => each[k],
+                })
+                .collect();
+            let mut group_piles = vec![Vec::new(); group.count];
+            // a row at a time across the group like a dealer would, so the Klondike example deals
+            // the same numbered games as the built in one
+            let rows = counts.iter().map(|(down, up)| down + up).max().unwrap_or(0);
+            for row in 0..rows {
+                for (pile, &(down, up)) in group_piles.iter_mut().zip(counts.iter()) {
+                    if row >= down + up {
+                        continue;
+                    }
+                    let card = deck.next().expect("validated to have enough cards");
+                    pile.push(if row < down {
+                        PileCard::down(card)
+                    } else {
+                        PileCard::up(card)
+                    });
+                }
+            }
+            piles.extend(group_piles);
+        }
+        let mut game = GameState {
+            pile_ids: definition.pile_ids(),
+            definition,
+            piles,
+            recycles: 0,
+        };
+        if let Some(stock) = game.pile_mut(PileId::Stock) {
+            stock.extend(deck.map(PileCard::down));
+        }
+        game
+    }
+
+    fn position(&self, id: PileId) -> Option<usize> {
+        self.pile_ids.iter().position(|&p| p == id)
+    }
+
+    fn rule_for(&self, id: PileId) -> Option<&BuildRule> {
+        match id {
+            PileId::Tableau(_) => Some(&self.definition.tableau),
+            PileId::Foundation(_) => Some(&self.definition.foundation),
+            _ => None,
+        }
+    }
+
+    fn tableau(&self) -> Vec<PileId> {
+        self.pile_ids
+            .iter()
+            .copied()
+            .filter(|p| matches!(p, PileId::Tableau(_)))
+            .collect()
+    }
+}
+
+impl Rules for GameState {
+    fn piles(&self) -> Vec<PileId> {
+        self.pile_ids.clone()
+    }
+
+    fn pile(&self, id: PileId) -> Option<&Vec<PileCard>> {
+        self.position(id).map(|i| &self.piles[i])
+    }
+
+    fn pile_mut(&mut self, id: PileId) -> Option<&mut Vec<PileCard>> {
+        self.position(id).map(|i| &mut self.piles[i])
+    }
+
+    fn check_stock(&self, mv: Move) -> Result<(), IllegalMove> {
+        let stock = self.pile(PileId::Stock);
+        if mv == Move::Draw {
+            if stock.map_or(0, |s| s.len()) == 0 {
+                return Err(IllegalMove::EmptyPile(PileId::Stock));
+            }
+            return Ok(());
+        }
+        let rule = self.definition.stock;
+        if rule.to != StockTarget::Waste {
+            return Err(IllegalMove::NotAllowed);
+        }
+        if stock.is_some_and(|s| !s.is_empty()) {
+            return Err(IllegalMove::StockNotEmpty);
+        }
+        if self.pile(PileId::Waste).map_or(0, |w| w.len()) == 0 {
+            return Err(IllegalMove::EmptyPile(PileId::Waste));
+        }
+        if let Some(max) = rule.passes {
+            if self.recycles + 1 >= max {
+                return Err(IllegalMove::NoPassesLeft);
+            }
+        }
+        Ok(())
+    }
+
+    fn turn_stock(&mut self, mv: Move) -> Applied {
+        if mv == Move::Draw {
+            let mut count = 0;
+            let targets = match self.definition.stock.to {
+                StockTarget::Waste => vec![PileId::Waste; self.definition.stock.draw],
+                StockTarget::Tableau => self.tableau(),
+            };
+            for to in targets {
+                let Some(card) = self.pile_mut(PileId::Stock).and_then(|s| s.pop()) else {
+                    break;
+                };
+                self.pile_mut(to)
+                    .expect("validated to exist")
+                    .push(PileCard::up(card.card));
+                count += 1;
+            }
+            return Applied::new(mv, count, false);
+        }
+        let waste = self.pile_mut(PileId::Waste).expect("checked above");
+        let cards: Vec<PileCard> = waste
+            .drain(..)
+            .rev()
+            .map(|c| PileCard::down(c.card))
+            .collect();
+        let count = cards.len();
+        *self.pile_mut(PileId::Stock).expect("checked above") = cards;
+        self.recycles += 1;
+        Applied::new(mv, count, false)
+    }
+
+    fn undo_stock(&mut self, applied: &Applied) {
+        if applied.mv == Move::Draw {
+            let sources = match self.definition.stock.to {
+                StockTarget::Waste => vec![PileId::Waste; applied.count],
+                StockTarget::Tableau => self.tableau()[..applied.count].to_vec(),
+            };
+            for from in sources.into_iter().rev() {
+                let card = self
+                    .pile_mut(from)
+                    .and_then(|p| p.pop())
+                    .expect("undoing a draw that never happened");
+                self.pile_mut(PileId::Stock)
+                    .expect("undoing a draw that never happened")
+                    .push(PileCard::down(card.card));
+            }
+            return;
+        }
+        let stock = self
+            .pile_mut(PileId::Stock)
+            .expect("undoing a recycle that never happened");
+        let cards: Vec<PileCard> = stock
+            .drain(..)
+            .rev()
+            .map(|c| PileCard::up(c.card))
+            .collect();
+        *self
+            .pile_mut(PileId::Waste)
+            .expect("undoing a recycle that never happened") = cards;
+        self.recycles -= 1;
+    }
+
+    fn picked_up(&self, from: PileId, count: usize) -> Result<&[PileCard], IllegalMove> {
+        let moving = self.top_cards(from, count)?;
+        let together = match from {
+            PileId::Stock => return Err(IllegalMove::NotAllowed),
+            PileId::Tableau(_) => match self.definition.tableau.moves {
+                Moves::One => count == 1,
+                Moves::Runs => moving
+                    .windows(2)
+                    .all(|w| builds(&self.definition.tableau, Some(&w[0]), &w[1].card)),
+                Moves::AnyFaceUp => true,
+            },
+            _ => count == 1,
+        };
+        if !together {
+            return Err(IllegalMove::NotASequence);
+        }
+        Ok(moving)
+    }
+
+    fn fits(
+        &self,
+        moving: &[PileCard],
+        to: PileId,
+        onto: &[PileCard],
+    ) -> Result<bool, IllegalMove> {
+        match to {
+            PileId::FreeCell(_) => Ok(moving.len() == 1 && onto.is_empty()),
+            PileId::Foundation(_) if moving.len() != 1 => Ok(false),
+            PileId::Tableau(_) | PileId::Foundation(_) => {
+                let rule = self
+                    .rule_for(to)
+                    .expect("tableau and foundations have rules");
+                Ok(builds(rule, onto.last(), &moving[0].card))
+            }
+            _ => Err(IllegalMove::NotAllowed),
+        }
+    }
+
+    fn is_won(&self) -> bool {
+        let piles = self.pile_ids.iter().copied().zip(self.piles.iter());
+        match self.definition.win {
+            Win::AllOnFoundations => piles
+                .filter(|(id, _)| !matches!(id, PileId::Foundation(_)))
+                .all(|(_, pile)| pile.is_empty()),
+            Win::TableauCleared => piles
+                .filter(|(id, _)| matches!(id, PileId::Tableau(_) | PileId::FreeCell(_)))
+                .all(|(_, pile)| pile.is_empty()),
+        }
+    }
+}
+
+/// `card` can go on `top`, or into the empty pile when there's no `top`, by `rule`.
+fn builds(rule: &BuildRule, top: Option<&PileCard>, card: &Card) -> bool {
+    let Some(top) = top else {
+        return match rule.empty {
+            Empty::Any => true,
+            Empty::Rank(rank) => card.number == rank,
+            Empty::Nothing => false,
+        };
+    };
+    if !top.face_up {
+        return false;
+    }
+    let lower = &top.card;
+    let next = |from: usize| if rule.wrap && from == 13 { 1 } else { from + 1 };
+    let up = next(lower.number) == card.number;
+    let down = next(card.number) == lower.number;
+    let ranks = match rule.direction {
+        Direction::Down => down,
+        Direction::Up => up,
+        Direction::Either => up || down,
+        Direction::Neither => false,
+    };
+    let suits = match rule.suits {
+        Suits::Any => true,
+        Suits::Alternate => lower.color != card.color,
+        Suits::SameColor => lower.color == card.color,
+        Suits::Same => lower.suit == card.suit,
+    };
+    ranks && suits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KLONDIKE: &str = "(
+        name: \"Klondike\",
+        piles: [
+            (kind: Stock, at: (0, 0)),
+            (kind: Waste, at: (1, 0), fan: Right(showing: 1)),
+            (kind: Foundation, at: (3, 0), count: 4),
+            (kind: Tableau, at: (0, 1), count: 7, fan: Down,
+             deal: [(0, 1), (1, 1), (2, 1), (3, 1), (4, 1), (5, 1), (6, 1)]),
+        ],
+        stock: (draw: 1),
+        tableau: (direction: Down, suits: Alternate, empty: Rank(13), moves: Runs),
+        win: AllOnFoundations,
+    )";
+
+    /// The reason `parse` gives for `KLONDIKE` with `from` swapped for `to`.
+    fn error_with(from: &str, to: &str) -> String {
+        assert!(KLONDIKE.contains(from), "{:?} isn't in the example", from);
+        match parse(&KLONDIKE.replacen(from, to, 1)) {
+            Ok(_) => panic!("{:?} loaded", to),
+            Err(DefinitionError::Invalid(e)) => e,
+            Err(e) => panic!("{:?} didn't parse: {}", to, e),
+        }
+    }
+
+    #[test]
+    fn every_shipped_variant_loads() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("variants");
+        let mut loaded = 0;
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "ron") {
+                let definition = load(&path).unwrap_or_else(|e| panic!("{:?}: {}", path, e));
+                let deck = definition.deck();
+                GameState::deal(Arc::new(definition), deck);
+                loaded += 1;
+            }
+        }
+        assert!(loaded > 0);
+    }
+
+    #[test]
+    fn the_module_example_loads() {
+        parse(KLONDIKE).unwrap();
+    }
+
+    #[test]
+    fn a_saved_game_comes_back_with_its_piles() {
+        let definition = parse(KLONDIKE).unwrap();
+        let deck = definition.deck();
+        let game = GameState::deal(Arc::new(definition), deck);
+        let text = ron::to_string(&game).unwrap();
+        assert!(!text.contains("pile_ids"));
+        let restored: GameState = ron::from_str(&text).unwrap();
+        assert_eq!(restored.piles(), game.definition.pile_ids());
+        assert_eq!(restored, game);
+    }
+
+    #[test]
+    fn says_what_is_wrong() {
+        let cases = [
+            (
+                "at: (0, 1)",
+                "at: (-1, 1)",
+                "piles[3] (Tableau) is at (-1, 1), it can't be negative",
+            ),
+            (
+                "count: 7,",
+                "count: 7, step: (1, -0.5),",
+                "piles[3] (Tableau) has a step of (1, -0.5), it can't be negative",
+            ),
+            (
+                "count: 4",
+                "count: 0",
+                "piles[2] (Foundation) has a count of 0",
+            ),
+            (
+                "(kind: Stock, at: (0, 0))",
+                "(kind: Stock, at: (0, 0), count: 2)",
+                "piles[0] (Stock) has a count of 2, there's only ever one",
+            ),
+            (
+                "(kind: Stock, at: (0, 0))",
+                "(kind: Stock, at: (0, 0), deal: [(0, 1)])",
+                "piles[0] (Stock) has a deal, the stock gets whatever's left",
+            ),
+            (
+                "(6, 1)]",
+                "]",
+                "piles[3] (Tableau) deals to 6 piles but has 7, give one entry for each or a \
+                 single one for all",
+            ),
+            (
+                "(0, 1), (1, 1)",
+                "(0, 50), (1, 1)",
+                "the deal needs 77 cards but 1 deck(s) only have 52",
+            ),
+            (
+                "(kind: Stock, at: (0, 0)),",
+                "",
+                "24 cards are left after the deal and there's no Stock to put them in",
+            ),
+            (
+                "(kind: Waste, at: (1, 0), fan: Right(showing: 1)),",
+                "",
+                "the stock draws onto the waste but there's no Waste",
+            ),
+            ("draw: 1", "draw: 0", "stock.draw is 0"),
+            (
+                "draw: 1",
+                "draw: 1, passes: Some(0)",
+                "stock.passes is 0, leave it out for no limit",
+            ),
+            (
+                "empty: Rank(13)",
+                "empty: Rank(14)",
+                "tableau.empty is Rank(14), ranks go from 1 to 13",
+            ),
+            (
+                "(kind: Foundation, at: (3, 0), count: 4),",
+                "",
+                "the win is AllOnFoundations but there's no Foundation",
+            ),
+            (
+                "name: \"Klondike\",",
+                "name: \"Klondike\", decks: 9,",
+                "decks is 9, it has to be 1 to 8",
+            ),
+        ];
+        for (from, to, why) in cases {
+            assert_eq!(error_with(from, to), why);
+        }
+    }
+
+    #[test]
+    fn unknown_fields_are_a_parse_error() {
+        let text = KLONDIKE.replacen("win:", "wins: AllOnFoundations, win:", 1);
+        assert!(matches!(parse(&text), Err(DefinitionError::Parse(_))));
+    }
+}
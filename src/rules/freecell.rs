@@ -0,0 +1,209 @@
+//! FreeCell, every card dealt face up into eight columns with four cells to park single cards
+//! in. Only one card really moves at a time, but a run moves together when there are enough free
+//! cells and empty columns to have shuffled it across one card at a time.
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    builds_down_alternating, builds_up_in_suit, Applied, Card, IllegalMove, Move, PileCard, PileId,
+    Rules,
+};
+
+pub const FREE_CELLS: usize = 4;
+pub const FOUNDATIONS: usize = 4;
+pub const TABLEAU: usize = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GameState {
+    /// Each holds one card at most.
+    pub cells: [Vec<PileCard>; FREE_CELLS],
+    pub foundations: [Vec<PileCard>; FOUNDATIONS],
+    pub tableau: [Vec<PileCard>; TABLEAU],
+}
+
+impl GameState {
+    /// Deals across the columns a row at a time, which with a `microsoft_deck` gives the same
+    /// layout as the Windows game with that number.
+    pub fn deal(deck: Vec<Card>) -> GameState {
+        let mut tableau: [Vec<PileCard>; TABLEAU] = Default::default();
+        for (i, card) in deck.into_iter().enumerate() {
+            tableau[i % TABLEAU].push(PileCard::up(card));
+        }
+        GameState {
+            cells: Default::default(),
+            foundations: Default::default(),
+            tableau,
+        }
+    }
+
+    /// The most cards that can move together onto `to`, (free cells + 1) doubled for every empty
+    /// column that isn't `to` itself.
+    pub fn max_run(&self, to: PileId) -> usize {
+        let free = self.cells.iter().filter(|c| c.is_empty()).count();
+        let empty = (0..TABLEAU)
+            .filter(|&t| self.tableau[t].is_empty() && PileId::Tableau(t) != to)
+            .count();
+        (free + 1) << empty
+    }
+}
+
+impl Rules for GameState {
+    fn piles(&self) -> Vec<PileId> {
+        (0..FREE_CELLS)
+            .map(PileId::FreeCell)
+            .chain((0..FOUNDATIONS).map(PileId::Foundation))
+            .chain((0..TABLEAU).map(PileId::Tableau))
+            .collect()
+    }
+
+    fn pile(&self, id: PileId) -> Option<&Vec<PileCard>> {
+        match id {
+            PileId::FreeCell(i) => self.cells.get(i),
+            PileId::Foundation(i) => self.foundations.get(i),
+            PileId::Tableau(i) => self.tableau.get(i),
+            _ => None,
+        }
+    }
+
+    fn pile_mut(&mut self, id: PileId) -> Option<&mut Vec<PileCard>> {
+        match id {
+            PileId::FreeCell(i) => self.cells.get_mut(i),
+            PileId::Foundation(i) => self.foundations.get_mut(i),
+            PileId::Tableau(i) => self.tableau.get_mut(i),
+            _ => None,
+        }
+    }
+
+    /// There's no stock, everything is dealt.
+    fn check_stock(&self, _mv: Move) -> Result<(), IllegalMove> {
+        Err(IllegalMove::NotAllowed)
+    }
+
+    fn turn_stock(&mut self, _mv: Move) -> Applied {
+        unreachable!("check_stock turns down everything but transfers");
+    }
+
+    fn undo_stock(&mut self, _applied: &Applied) {
+        unreachable!("check_stock turns down everything but transfers");
+    }
+
+    /// As many as could go onto a column that already has cards.
+    fn can_pick_up(&self, from: PileId, count: usize) -> bool {
+        self.picked_up(from, count).is_ok() && count <= self.max_run(from)
+    }
+
+    fn picked_up(&self, from: PileId, count: usize) -> Result<&[PileCard], IllegalMove> {
+        let moving = self.top_cards(from, count)?;
+        match from {
+            PileId::Tableau(_) => {
+                if moving
+                    .windows(2)
+                    .any(|w| !builds_down_alternating(&w[0].card, &w[1].card))
+                {
+                    return Err(IllegalMove::NotASequence);
+                }
+            }
+            PileId::FreeCell(_) => {}
+            // cards that have gone up stay up
+            _ => return Err(IllegalMove::NotAllowed),
+        }
+        Ok(moving)
+    }
+
+    fn fits(
+        &self,
+        moving: &[PileCard],
+        to: PileId,
+        onto: &[PileCard],
+    ) -> Result<bool, IllegalMove> {
+        let bottom = &moving[0].card;
+        match to {
+            PileId::FreeCell(_) => Ok(moving.len() == 1 && onto.is_empty()),
+            PileId::Foundation(_) => {
+                Ok(moving.len() == 1 && builds_up_in_suit(onto.last().map(|c| &c.card), bottom))
+            }
+            PileId::Tableau(_) => {
+                if moving.len() > self.max_run(to) {
+                    return Err(IllegalMove::TooManyCards);
+                }
+                Ok(match onto.last() {
+                    None => true,
+                    Some(top) => builds_down_alternating(&top.card, bottom),
+                })
+            }
+            _ => Err(IllegalMove::NotAllowed),
+        }
+    }
+
+    fn is_won(&self) -> bool {
+        self.foundations.iter().all(|f| f.len() == 13)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::test_cards::{freecell_table, up};
+    use crate::rules::CardSuit;
+
+    /// A four card run in column 0 that fits on column 1, three cells taken and column 7 the only
+    /// empty one.
+    fn crowded_table() -> GameState {
+        let mut game = freecell_table();
+        game.tableau[0] = vec![
+            up(9, CardSuit::Clubs),
+            up(8, CardSuit::Hearts),
+            up(7, CardSuit::Spades),
+            up(6, CardSuit::Diamonds),
+        ];
+        game.tableau[1] = vec![up(10, CardSuit::Hearts)];
+        for col in 2..7 {
+            game.tableau[col] = vec![up(col + 1, CardSuit::Clubs)];
+        }
+        for (cell, number) in game.cells.iter_mut().zip([11, 12, 13]) {
+            cell.push(up(number, CardSuit::Diamonds));
+        }
+        game
+    }
+
+    fn run(to: usize, count: usize) -> Move {
+        Move::Transfer {
+            from: PileId::Tableau(0),
+            to: PileId::Tableau(to),
+            count,
+        }
+    }
+
+    #[test]
+    fn runs_double_for_every_empty_column() {
+        let mut game = freecell_table();
+        game.tableau[0] = vec![up(1, CardSuit::Clubs)];
+        game.tableau[1] = vec![up(2, CardSuit::Clubs)];
+        // four cells and six empty columns
+        assert_eq!(game.max_run(PileId::Tableau(0)), 5 << 6);
+        game.cells[0].push(up(3, CardSuit::Clubs));
+        game.cells[1].push(up(4, CardSuit::Clubs));
+        assert_eq!(game.max_run(PileId::Tableau(0)), 3 << 6);
+        // the column being moved into can't also hold cards on the way
+        assert_eq!(game.max_run(PileId::Tableau(2)), 3 << 5);
+    }
+
+    #[test]
+    fn a_run_too_long_for_the_cells_and_columns_is_refused() {
+        let mut game = crowded_table();
+        // one free cell and one empty column, (1 + 1) << 1
+        assert_eq!(game.max_run(PileId::Tableau(1)), 4);
+        assert_eq!(game.check(run(1, 4)), Ok(()));
+        game.tableau[7] = vec![up(13, CardSuit::Spades)];
+        assert_eq!(game.max_run(PileId::Tableau(1)), 2);
+        assert_eq!(game.check(run(1, 4)), Err(IllegalMove::TooManyCards));
+    }
+
+    #[test]
+    fn moving_into_the_empty_column_leaves_it_out_of_the_count() {
+        let game = crowded_table();
+        assert_eq!(game.max_run(PileId::Tableau(7)), 2);
+        assert_eq!(game.check(run(7, 2)), Ok(()));
+        assert_eq!(game.check(run(7, 3)), Err(IllegalMove::TooManyCards));
+    }
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use super::{variant::VariantState, Applied, IllegalMove, Move};
+
+/// Every move played so far plus the ones taken back, so they can be played again.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct History {
+    played: Vec<Applied>,
+    undone: Vec<Move>,
+}
+
+impl History {
+    /// A fresh move wipes out anything that could have been redone.
+    pub fn record(&mut self, applied: Applied) {
+        self.played.push(applied);
+        self.undone.clear();
+    }
+
+    /// How many moves it took to get here, undone ones don't count.
+    pub fn moves_played(&self) -> usize {
+        self.played.len()
+    }
+
+    /// The moves behind the current position, oldest first.
+    pub fn played(&self) -> &[Applied] {
+        &self.played
+    }
+
+    pub fn undo(&mut self, state: &mut VariantState) -> Option<Applied> {
+        let applied = self.played.pop()?;
+        state.undo(&applied);
+        self.undone.push(applied.mv);
+        Some(applied)
+    }
+
+    /// Nothing to redo is `Ok(None)`. The rules only turn a redo down if the state was changed
+    /// behind our back, and then the rest of what was undone is stale too so it all goes.
+    pub fn redo(&mut self, state: &mut VariantState) -> Result<Option<Applied>, IllegalMove> {
+        let Some(mv) = self.undone.pop() else {
+            return Ok(None);
+        };
+        match state.apply(mv) {
+            Ok(applied) => {
+                self.played.push(applied);
+                Ok(Some(applied))
+            }
+            Err(e) => {
+                self.undone.clear();
+                Err(e)
+            }
+        }
+    }
+}
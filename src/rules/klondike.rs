@@ -0,0 +1,418 @@
+use super::{
+    builds_down_alternating, builds_up_in_suit, Applied, Card, IllegalMove, Move, PileCard, PileId,
+    Rules,
+};
+use serde::{Deserialize, Serialize};
+
+pub const FOUNDATIONS: usize = 4;
+pub const TABLEAU: usize = 7;
+
+/// How the stock plays, picked when the game is dealt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StockRules {
+    /// Cards turned over per click, 1 or 3.
+    pub draw: usize,
+    /// How many times you get to go through the stock, `None` for as often as you like.
+    pub max_passes: Option<u32>,
+}
+
+impl Default for StockRules {
+    fn default() -> StockRules {
+        StockRules {
+            draw: 1,
+            max_passes: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GameState {
+    pub stock: Vec<PileCard>,
+    pub waste: Vec<PileCard>,
+    pub foundations: [Vec<PileCard>; FOUNDATIONS],
+    pub tableau: [Vec<PileCard>; TABLEAU],
+    pub stock_rules: StockRules,
+    /// Times the waste has been turned back into the stock.
+    pub recycles: u32,
+}
+
+impl GameState {
+    /// Deals `deck` the usual way, row by row across the columns so column `i` ends up with
+    /// `i + 1` cards and only the last one face up. Whatever is left over is the stock, with the
+    /// end of `deck` on top.
+    pub fn deal(deck: Vec<Card>, stock_rules: StockRules) -> GameState {
+        let mut deck = deck.into_iter();
+        let mut tableau: [Vec<PileCard>; TABLEAU] = Default::default();
+        for row in 0..TABLEAU {
+            for (col, pile) in tableau.iter_mut().enumerate().skip(row) {
+                let card = deck
+                    .next()
+                    .expect("Not enough cards in the deck to deal Klondike.");
+                pile.push(if col == row {
+                    PileCard::up(card)
+                } else {
+                    PileCard::down(card)
+                });
+            }
+        }
+        GameState {
+            stock: deck.map(PileCard::down).collect(),
+            waste: Vec::new(),
+            foundations: Default::default(),
+            tableau,
+            stock_rules,
+            recycles: 0,
+        }
+    }
+}
+
+impl Rules for GameState {
+    fn piles(&self) -> Vec<PileId> {
+        [PileId::Stock, PileId::Waste]
+            .into_iter()
+            .chain((0..FOUNDATIONS).map(PileId::Foundation))
+            .chain((0..TABLEAU).map(PileId::Tableau))
+            .collect()
+    }
+
+    fn pile(&self, id: PileId) -> Option<&Vec<PileCard>> {
+        match id {
+            PileId::Stock => Some(&self.stock),
+            PileId::Waste => Some(&self.waste),
+            PileId::Foundation(i) => self.foundations.get(i),
+            PileId::Tableau(i) => self.tableau.get(i),
+            PileId::FreeCell(_) | PileId::Spot(_) => None,
+        }
+    }
+
+    fn pile_mut(&mut self, id: PileId) -> Option<&mut Vec<PileCard>> {
+        match id {
+            PileId::Stock => Some(&mut self.stock),
+            PileId::Waste => Some(&mut self.waste),
+            PileId::Foundation(i) => self.foundations.get_mut(i),
+            PileId::Tableau(i) => self.tableau.get_mut(i),
+            PileId::FreeCell(_) | PileId::Spot(_) => None,
+        }
+    }
+
+    fn check_stock(&self, mv: Move) -> Result<(), IllegalMove> {
+        if mv == Move::Draw {
+            if self.stock.is_empty() {
+                return Err(IllegalMove::EmptyPile(PileId::Stock));
+            }
+            return Ok(());
+        }
+        if !self.stock.is_empty() {
+            return Err(IllegalMove::StockNotEmpty);
+        }
+        if self.waste.is_empty() {
+            return Err(IllegalMove::EmptyPile(PileId::Waste));
+        }
+        if let Some(max) = self.stock_rules.max_passes {
+            if self.recycles + 1 >= max {
+                return Err(IllegalMove::NoPassesLeft);
+            }
+        }
+        Ok(())
+    }
+
+    fn turn_stock(&mut self, mv: Move) -> Applied {
+        if mv == Move::Draw {
+            let count = self.stock_rules.draw.min(self.stock.len());
+            for _ in 0..count {
+                let mut card = self.stock.pop().expect("counted above");
+                card.face_up = true;
+                self.waste.push(card);
+            }
+            return Applied::new(mv, count, false);
+        }
+        let count = self.waste.len();
+        self.stock = self
+            .waste
+            .drain(..)
+            .rev()
+            .map(|c| PileCard::down(c.card))
+            .collect();
+        self.recycles += 1;
+        Applied::new(mv, count, false)
+    }
+
+    fn undo_stock(&mut self, applied: &Applied) {
+        if applied.mv == Move::Draw {
+            let at = self.waste.len() - applied.count;
+            let drawn = self.waste.split_off(at);
+            self.stock
+                .extend(drawn.into_iter().rev().map(|c| PileCard::down(c.card)));
+            return;
+        }
+        self.waste = self
+            .stock
+            .drain(..)
+            .rev()
+            .map(|c| PileCard::up(c.card))
+            .collect();
+        self.recycles -= 1;
+    }
+
+    /// Only whole built runs leave the tableau, everywhere else you take the top card.
+    fn picked_up(&self, from: PileId, count: usize) -> Result<&[PileCard], IllegalMove> {
+        let moving = self.top_cards(from, count)?;
+        match from {
+            PileId::Tableau(_) => {
+                if moving
+                    .windows(2)
+                    .any(|w| !builds_down_alternating(&w[0].card, &w[1].card))
+                {
+                    return Err(IllegalMove::NotASequence);
+                }
+            }
+            PileId::Waste | PileId::Foundation(_) => {
+                if count != 1 {
+                    return Err(IllegalMove::NotASequence);
+                }
+            }
+            PileId::Stock | PileId::FreeCell(_) | PileId::Spot(_) => {
+                return Err(IllegalMove::NotAllowed)
+            }
+        }
+        Ok(moving)
+    }
+
+    fn fits(
+        &self,
+        moving: &[PileCard],
+        to: PileId,
+        onto: &[PileCard],
+    ) -> Result<bool, IllegalMove> {
+        let bottom = &moving[0].card;
+        match to {
+            PileId::Foundation(_) => {
+                Ok(moving.len() == 1 && builds_up_in_suit(onto.last().map(|c| &c.card), bottom))
+            }
+            PileId::Tableau(_) => Ok(match onto.last() {
+                None => bottom.number == 13,
+                Some(top) => top.face_up && builds_down_alternating(&top.card, bottom),
+            }),
+            PileId::Stock | PileId::Waste | PileId::FreeCell(_) | PileId::Spot(_) => {
+                Err(IllegalMove::NotAllowed)
+            }
+        }
+    }
+
+    fn is_won(&self) -> bool {
+        self.foundations.iter().all(|f| f.len() == 13)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::rules::test_cards::{card, klondike_table};
+    use crate::rules::{new_deck, shuffle::DealSeed, CardSuit};
+
+    fn transfer(from: PileId, to: PileId, count: usize) -> Move {
+        Move::Transfer { from, to, count }
+    }
+
+    #[test]
+    fn deals_one_to_seven_with_the_rest_on_the_stock() {
+        let deck = new_deck();
+        let game = GameState::deal(deck.clone(), StockRules::default());
+        for (i, column) in game.tableau.iter().enumerate() {
+            assert_eq!(column.len(), i + 1);
+            assert!(column[..i].iter().all(|c| !c.face_up));
+            assert!(column[i].face_up);
+        }
+        assert_eq!(game.stock.len(), 24);
+        assert!(game.stock.iter().all(|c| !c.face_up));
+        assert_eq!(game.stock.last().map(|c| c.card), deck.last().copied());
+        assert!(game.waste.is_empty());
+        assert!(game.foundations.iter().all(|f| f.is_empty()));
+        // row by row, so the second card is the top of the second column
+        assert_eq!(game.tableau[0][0].card, deck[0]);
+        assert_eq!(game.tableau[1][0].card, deck[1]);
+        assert_eq!(game.tableau[1][1].card, deck[7]);
+    }
+
+    #[test]
+    fn builds_down_in_alternating_colours_and_up_the_foundations() {
+        let mut game = klondike_table(StockRules::default());
+        game.tableau[0] = vec![
+            PileCard::down(card(2, CardSuit::Clubs)),
+            PileCard::up(card(7, CardSuit::Spades)),
+        ];
+        game.tableau[1] = vec![
+            PileCard::up(card(6, CardSuit::Hearts)),
+            PileCard::up(card(5, CardSuit::Clubs)),
+        ];
+        game.tableau[2] = vec![PileCard::up(card(8, CardSuit::Diamonds))];
+        game.tableau[3] = vec![PileCard::up(card(13, CardSuit::Hearts))];
+        game.waste = vec![PileCard::up(card(1, CardSuit::Hearts))];
+
+        let applied = game
+            .apply(transfer(PileId::Tableau(1), PileId::Tableau(0), 2))
+            .unwrap();
+        assert!(!applied.flipped);
+        assert_eq!(game.tableau[0].len(), 4);
+        game.apply(transfer(PileId::Waste, PileId::Foundation(3), 1))
+            .unwrap();
+        // a King into the column that just emptied
+        game.apply(transfer(PileId::Tableau(3), PileId::Tableau(1), 1))
+            .unwrap();
+        // moving the run off turns the card under it over
+        let applied = game
+            .apply(transfer(PileId::Tableau(0), PileId::Tableau(2), 3))
+            .unwrap();
+        assert!(applied.flipped);
+        assert!(game.tableau[0][0].face_up);
+        assert_eq!(game.tableau[2].len(), 4);
+        assert_eq!(game.foundations[3].len(), 1);
+    }
+
+    #[test]
+    fn turns_down_moves_that_break_the_rules() {
+        let mut game = klondike_table(StockRules::default());
+        game.tableau[0] = vec![
+            PileCard::down(card(9, CardSuit::Hearts)),
+            PileCard::up(card(8, CardSuit::Spades)),
+        ];
+        game.tableau[1] = vec![
+            PileCard::up(card(7, CardSuit::Clubs)),
+            PileCard::up(card(3, CardSuit::Diamonds)),
+        ];
+        game.tableau[2] = vec![PileCard::up(card(12, CardSuit::Hearts))];
+        game.waste = vec![
+            PileCard::up(card(2, CardSuit::Hearts)),
+            PileCard::up(card(7, CardSuit::Hearts)),
+        ];
+        game.stock = vec![PileCard::down(card(4, CardSuit::Spades))];
+        let cases = [
+            // same colour
+            (
+                transfer(PileId::Tableau(1), PileId::Tableau(0), 1),
+                IllegalMove::DoesNotFit,
+            ),
+            // only Kings go in an empty column
+            (
+                transfer(PileId::Tableau(2), PileId::Tableau(3), 1),
+                IllegalMove::DoesNotFit,
+            ),
+            // foundations start with an Ace
+            (
+                transfer(PileId::Waste, PileId::Foundation(0), 1),
+                IllegalMove::DoesNotFit,
+            ),
+            (
+                transfer(PileId::Tableau(1), PileId::Tableau(0), 2),
+                IllegalMove::NotASequence,
+            ),
+            (
+                transfer(PileId::Tableau(0), PileId::Tableau(2), 2),
+                IllegalMove::FaceDown,
+            ),
+            (
+                transfer(PileId::Waste, PileId::Tableau(0), 2),
+                IllegalMove::NotASequence,
+            ),
+            // the stock only ever turns over
+            (
+                transfer(PileId::Stock, PileId::Tableau(1), 1),
+                IllegalMove::FaceDown,
+            ),
+            (
+                transfer(PileId::Tableau(0), PileId::Tableau(0), 1),
+                IllegalMove::NotAllowed,
+            ),
+            (
+                transfer(PileId::Tableau(3), PileId::Tableau(2), 1),
+                IllegalMove::EmptyPile(PileId::Tableau(3)),
+            ),
+            (
+                transfer(PileId::Tableau(0), PileId::FreeCell(0), 1),
+                IllegalMove::UnknownPile(PileId::FreeCell(0)),
+            ),
+            (Move::Recycle, IllegalMove::StockNotEmpty),
+        ];
+        let before = game.clone();
+        for (mv, why) in cases {
+            assert_eq!(game.apply(mv), Err(why), "{:?}", mv);
+        }
+        assert_eq!(game, before);
+    }
+
+    #[test]
+    fn draws_three_and_recycles_until_the_passes_run_out() {
+        let rules = StockRules {
+            draw: 3,
+            max_passes: Some(2),
+        };
+        let mut game = klondike_table(rules);
+        game.stock = (1..=4)
+            .map(|n| PileCard::down(card(n, CardSuit::Spades)))
+            .collect();
+
+        let applied = game.apply(Move::Draw).unwrap();
+        assert_eq!(applied.count, 3);
+        // the top of the stock ends up under the other two
+        let waste: Vec<usize> = game.waste.iter().map(|c| c.card.number).collect();
+        assert_eq!(waste, [4, 3, 2]);
+        assert!(game.waste.iter().all(|c| c.face_up));
+        assert_eq!(game.apply(Move::Draw).unwrap().count, 1);
+        assert_eq!(
+            game.apply(Move::Draw),
+            Err(IllegalMove::EmptyPile(PileId::Stock))
+        );
+
+        game.apply(Move::Recycle).unwrap();
+        assert_eq!(game.recycles, 1);
+        assert!(game.waste.is_empty());
+        assert!(game.stock.iter().all(|c| !c.face_up));
+        assert_eq!(game.stock.last().map(|c| c.card.number), Some(4));
+        while game.apply(Move::Draw).is_ok() {}
+        // two passes through, so only the one recycle
+        assert_eq!(game.apply(Move::Recycle), Err(IllegalMove::NoPassesLeft));
+    }
+
+    #[test]
+    fn recycling_an_empty_waste_is_turned_down() {
+        let mut game = klondike_table(StockRules::default());
+        assert_eq!(
+            game.apply(Move::Recycle),
+            Err(IllegalMove::EmptyPile(PileId::Waste))
+        );
+    }
+
+    #[test]
+    fn undo_takes_random_games_back_to_the_deal() {
+        let all_rules = [
+            StockRules::default(),
+            StockRules {
+                draw: 3,
+                max_passes: Some(3),
+            },
+        ];
+        for stock_rules in all_rules {
+            for seed in 0..200 {
+                let dealt = GameState::deal(DealSeed::Seed(seed).deck(), stock_rules);
+                let mut game = dealt.clone();
+                let mut rng = StdRng::seed_from_u64(seed);
+                let mut played = Vec::new();
+                for _ in 0..300 {
+                    let moves = game.legal_moves();
+                    if moves.is_empty() {
+                        break;
+                    }
+                    let mv = moves[rng.gen_range(0..moves.len())];
+                    played.push((game.clone(), game.apply(mv).unwrap()));
+                }
+                while let Some((before, applied)) = played.pop() {
+                    game.undo(&applied);
+                    assert_eq!(game, before, "seed {} undoing {:?}", seed, applied);
+                }
+                assert_eq!(game, dealt);
+            }
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! Plain Rust game model, nothing in here knows about Bevy systems or transforms. The ECS side
+//! asks these types what is legal and renders whatever state they end up in.
+
+use serde::{Deserialize, Serialize};
+
+pub mod autoplay;
+pub mod custom;
+pub mod freecell;
+pub mod history;
+pub mod klondike;
+pub mod overlap;
+pub mod save;
+pub mod scoring;
+pub mod shuffle;
+pub mod solver;
+pub mod spider;
+pub mod variant;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CardSuit {
+    Hearts,
+    Spades,
+    Diamonds,
+    Clubs,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CardColor {
+    Red,
+    Black,
+}
+
+/// One playing card. The board wraps it in a `CardVisual` to draw it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Card {
+    /// Its place in cards.png, counting from 1.
+    pub index: usize,
+    pub number: usize,
+    pub suit: CardSuit,
+    pub color: CardColor,
+    /// Which copy of the deck this card came from, games with two decks have two of everything.
+    pub deck: usize,
+}
+
+impl Card {
+    /// Builds the card for position `i` (0..52) in cards.png, rows are hearts, spades, diamonds
+    /// and clubs, each running Ace to King.
+    pub fn from_index(i: usize) -> Card {
+        let (suit, color) = match i / 13 {
+            0 => (CardSuit::Hearts, CardColor::Red),
+            1 => (CardSuit::Spades, CardColor::Black),
+            2 => (CardSuit::Diamonds, CardColor::Red),
+            _ => (CardSuit::Clubs, CardColor::Black),
+        };
+        Card {
+            index: i + 1,
+            number: (i % 13) + 1,
+            suit,
+            color,
+            deck: 0,
+        }
+    }
+
+    /// The same card out of another copy of the deck.
+    pub fn in_deck(self, deck: usize) -> Card {
+        Card { deck, ..self }
+    }
+
+    /// Tells apart every card on the table, `index` alone doesn't once there's more than one
+    /// deck.
+    pub fn id(&self) -> usize {
+        self.deck * 52 + self.index
+    }
+}
+
+/// A card sitting in a pile, along with which way up it is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PileCard {
+    pub card: Card,
+    pub face_up: bool,
+}
+
+impl PileCard {
+    pub fn up(card: Card) -> PileCard {
+        PileCard {
+            card,
+            face_up: true,
+        }
+    }
+
+    pub fn down(card: Card) -> PileCard {
+        PileCard {
+            card,
+            face_up: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PileId {
+    Stock,
+    Waste,
+    Foundation(usize),
+    Tableau(usize),
+    /// FreeCell's spaces for a single card.
+    FreeCell(usize),
+    /// Where one card of an overlapping layout like Pyramid's sits.
+    Spot(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Move {
+    /// Turn cards over off the stock, onto the waste or in Spider one onto every column.
+    Draw,
+    /// Turn the whole waste back over into an empty stock.
+    Recycle,
+    /// Move the top `count` cards of `from` onto `to`.
+    Transfer {
+        from: PileId,
+        to: PileId,
+        count: usize,
+    },
+}
+
+/// What actually happened when a `Move` was applied, enough to tell the renderer which cards
+/// changed and to put everything back again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Applied {
+    pub mv: Move,
+    /// How many cards changed pile.
+    pub count: usize,
+    /// The move left a face down card on top of a tableau column and it got turned over.
+    pub flipped: bool,
+    /// Tableau columns, as bits, that ended up with a finished King to Ace run that was lifted
+    /// off onto the foundations. Only Spider does this.
+    pub cleared: u16,
+    /// Which of the `cleared` columns then had a face down card turned over.
+    pub uncovered: u16,
+}
+
+impl Applied {
+    /// A move that didn't clear anything away.
+    pub fn new(mv: Move, count: usize, flipped: bool) -> Applied {
+        Applied {
+            mv,
+            count,
+            flipped,
+            cleared: 0,
+            uncovered: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IllegalMove {
+    UnknownPile(PileId),
+    EmptyPile(PileId),
+    FaceDown,
+    /// Another card still lies across it.
+    Covered,
+    NotASequence,
+    DoesNotFit,
+    StockNotEmpty,
+    NoPassesLeft,
+    /// More cards than the free cells and empty columns would let you move one at a time.
+    TooManyCards,
+    NotAllowed,
+}
+
+/// What every variant's `GameState` does. A variant says which piles it has, what the stock
+/// does, what can be picked up and what it fits on, and checking, playing and taking back moves
+/// is worked out from that the same way for all of them. `VariantState` dispatches through it.
+pub trait Rules {
+    fn piles(&self) -> Vec<PileId>;
+
+    fn pile(&self, id: PileId) -> Option<&Vec<PileCard>>;
+
+    fn pile_mut(&mut self, id: PileId) -> Option<&mut Vec<PileCard>>;
+
+    /// Whether a `Move::Draw` or `Move::Recycle` can be played.
+    fn check_stock(&self, mv: Move) -> Result<(), IllegalMove>;
+
+    /// Plays a `Move::Draw` or `Move::Recycle` that `check_stock` let through.
+    fn turn_stock(&mut self, mv: Move) -> Applied;
+
+    /// Takes back what `turn_stock` did.
+    fn undo_stock(&mut self, applied: &Applied);
+
+    /// The top `count` cards of `from`, if they can be lifted off together, before worrying
+    /// about where they go.
+    fn picked_up(&self, from: PileId, count: usize) -> Result<&[PileCard], IllegalMove>;
+
+    /// Whether `moving` can go onto `to`, which holds `onto`. Turning a move down with a reason
+    /// of its own is up to the variant, plain `false` comes back as `IllegalMove::DoesNotFit`.
+    fn fits(&self, moving: &[PileCard], to: PileId, onto: &[PileCard])
+        -> Result<bool, IllegalMove>;
+
+    fn is_won(&self) -> bool;
+
+    fn check(&self, mv: Move) -> Result<(), IllegalMove> {
+        let Move::Transfer { from, to, count } = mv else {
+            return self.check_stock(mv);
+        };
+        let onto = self.pile(to).ok_or(IllegalMove::UnknownPile(to))?;
+        if from == to {
+            return Err(IllegalMove::NotAllowed);
+        }
+        let moving = self.picked_up(from, count)?;
+        if !self.fits(moving, to, onto)? {
+            return Err(IllegalMove::DoesNotFit);
+        }
+        Ok(())
+    }
+
+    fn can_pick_up(&self, from: PileId, count: usize) -> bool {
+        self.picked_up(from, count).is_ok()
+    }
+
+    /// Turning the stock over first, then every transfer in the order of `piles`.
+    fn legal_moves(&self) -> Vec<Move> {
+        let mut moves: Vec<Move> = [Move::Draw, Move::Recycle]
+            .into_iter()
+            .filter(|mv| self.check(*mv).is_ok())
+            .collect();
+        let piles = self.piles();
+        for &from in piles.iter() {
+            let len = self.pile(from).map_or(0, |p| p.len());
+            for count in 1..=len {
+                for &to in piles.iter() {
+                    let mv = Move::Transfer { from, to, count };
+                    if self.check(mv).is_ok() {
+                        moves.push(mv);
+                    }
+                }
+            }
+        }
+        moves
+    }
+
+    fn apply(&mut self, mv: Move) -> Result<Applied, IllegalMove> {
+        self.check(mv)?;
+        let mut applied = match mv {
+            Move::Transfer { from, to, count } => self.transfer(from, to, count),
+            Move::Draw | Move::Recycle => self.turn_stock(mv),
+        };
+        self.settle(&mut applied);
+        Ok(applied)
+    }
+
+    /// Puts back exactly what `apply` did for `applied`, which has to be the last thing applied.
+    fn undo(&mut self, applied: &Applied) {
+        self.unsettle(applied);
+        match applied.mv {
+            Move::Transfer { .. } => self.undo_transfer(applied),
+            Move::Draw | Move::Recycle => self.undo_stock(applied),
+        }
+    }
+
+    /// Moves the cards of a transfer `check` let through, turning over a face down card it
+    /// leaves on top of a tableau column.
+    fn transfer(&mut self, from: PileId, to: PileId, count: usize) -> Applied {
+        let source = self.pile_mut(from).expect("checked above");
+        let moving = source.split_off(source.len() - count);
+        let flipped = match source.last_mut() {
+            Some(top) if !top.face_up && matches!(from, PileId::Tableau(_)) => {
+                top.face_up = true;
+                true
+            }
+            _ => false,
+        };
+        self.pile_mut(to).expect("checked above").extend(moving);
+        Applied::new(Move::Transfer { from, to, count }, count, flipped)
+    }
+
+    /// Takes back what `transfer` did.
+    fn undo_transfer(&mut self, applied: &Applied) {
+        let Move::Transfer { from, to, count } = applied.mv else {
+            return;
+        };
+        let dest = self
+            .pile_mut(to)
+            .expect("undoing a move that was never legal");
+        let moving = dest.split_off(dest.len() - count);
+        let source = self
+            .pile_mut(from)
+            .expect("undoing a move that was never legal");
+        if applied.flipped {
+            if let Some(top) = source.last_mut() {
+                top.face_up = false;
+            }
+        }
+        source.extend(moving);
+    }
+
+    /// Whatever happens by itself once a move has been played, like Spider lifting off a
+    /// finished run, noted down in `applied` so `unsettle` can put it back.
+    fn settle(&mut self, _applied: &mut Applied) {}
+
+    /// Takes back what `settle` did, before the move itself gets taken back.
+    fn unsettle(&mut self, _applied: &Applied) {}
+
+    /// The top `count` cards of `from`, as long as it has that many and they're all face up.
+    fn top_cards(&self, from: PileId, count: usize) -> Result<&[PileCard], IllegalMove> {
+        let source = self.pile(from).ok_or(IllegalMove::UnknownPile(from))?;
+        if count == 0 {
+            return Err(IllegalMove::NotAllowed);
+        }
+        if source.len() < count {
+            return Err(IllegalMove::EmptyPile(from));
+        }
+        let moving = &source[source.len() - count..];
+        if moving.iter().any(|c| !c.face_up) {
+            return Err(IllegalMove::FaceDown);
+        }
+        Ok(moving)
+    }
+}
+
+/// A deck in cards.png order, Ace to King of hearts, spades, diamonds then clubs.
+pub fn new_deck() -> Vec<Card> {
+    (0..52).map(Card::from_index).collect()
+}
+
+/// `upper` can sit on `lower` in a tableau column, one rank lower and the other colour.
+pub fn builds_down_alternating(lower: &Card, upper: &Card) -> bool {
+    upper.number + 1 == lower.number && upper.color != lower.color
+}
+
+/// `card` can go on a foundation whose top card is `top`.
+pub fn builds_up_in_suit(top: Option<&Card>, card: &Card) -> bool {
+    match top {
+        None => card.number == 1,
+        Some(top) => top.suit == card.suit && top.number + 1 == card.number,
+    }
+}
+
+/// Cards and empty tables for the tests to lay out just the positions they need.
+#[cfg(test)]
+pub(crate) mod test_cards {
+    use super::{freecell, klondike, Card, CardSuit, PileCard};
+
+    pub fn card(number: usize, suit: CardSuit) -> Card {
+        let row = match suit {
+            CardSuit::Hearts => 0,
+            CardSuit::Spades => 1,
+            CardSuit::Diamonds => 2,
+            CardSuit::Clubs => 3,
+        };
+        Card::from_index(row * 13 + number - 1)
+    }
+
+    pub fn up(number: usize, suit: CardSuit) -> PileCard {
+        PileCard::up(card(number, suit))
+    }
+
+    pub fn down(number: usize, suit: CardSuit) -> PileCard {
+        PileCard::down(card(number, suit))
+    }
+
+    /// A Klondike game with nothing anywhere.
+    pub fn klondike_table(stock_rules: klondike::StockRules) -> klondike::GameState {
+        klondike::GameState {
+            stock: Vec::new(),
+            waste: Vec::new(),
+            foundations: Default::default(),
+            tableau: Default::default(),
+            stock_rules,
+            recycles: 0,
+        }
+    }
+
+    /// A FreeCell game with nothing anywhere.
+    pub fn freecell_table() -> freecell::GameState {
+        freecell::GameState {
+            cells: Default::default(),
+            foundations: Default::default(),
+            tableau: Default::default(),
+        }
+    }
+}
@@ -0,0 +1,458 @@
+//! Games dealt as overlapping cards instead of piles: Pyramid, Golf and TriPeaks. Every card on
+//! the table gets a spot of its own, and it can only be played once nothing lies across it. Which
+//! spots lie across which gets worked out from where they are when the cards are dealt, and is
+//! kept with the game rather than read back off the screen.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Applied, Card, IllegalMove, Move, PileCard, PileId};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Rules {
+    /// Take off pairs adding up to 13, Kings on their own.
+    Pyramid,
+    /// Play cards a rank up or down onto the waste, no going round the corner.
+    Golf,
+    /// Like Golf on three peaks that start face down, and Kings and Aces go on each other.
+    TriPeaks,
+}
+
+/// Where a card gets dealt, in card sized steps like `PileSlot`. Spots on the next layer up lie
+/// across the ones they're within half a card of.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spot {
+    pub column: f32,
+    pub row: f32,
+    pub layer: usize,
+}
+
+/// Times through the stock in Pyramid.
+const PYRAMID_PASSES: usize = 3;
+
+impl Rules {
+    /// The spots in the order they're dealt to.
+    pub fn spots(self) -> Vec<Spot> {
+        let spot = |column: f32, layer: usize, row_step: f32| Spot {
+            column,
+            row: layer as f32 * row_step,
+            layer,
+        };
+        match self {
+            Rules::Pyramid => (0..7)
+                .flat_map(|layer| {
+                    (0..=layer).map(move |i| spot(3.0 - layer as f32 * 0.5 + i as f32, layer, 0.5))
+                })
+                .collect(),
+            Rules::Golf => (0..5)
+                .flat_map(|layer| (0..7).map(move |column| spot(column as f32, layer, 0.25)))
+                .collect(),
+            Rules::TriPeaks => {
+                let peaks = [1.5, 4.5, 7.5];
+                let tops = peaks.map(|x| spot(x, 0, 0.5));
+                let shoulders = peaks
+                    .into_iter()
+                    .flat_map(|x| [x - 0.5, x + 0.5])
+                    .map(|x| spot(x, 1, 0.5));
+                let third = (0..9).map(|i| spot(0.5 + i as f32, 2, 0.5));
+                let bottom = (0..10).map(|i| spot(i as f32, 3, 0.5));
+                tops.into_iter()
+                    .chain(shoulders)
+                    .chain(third)
+                    .chain(bottom)
+                    .collect()
+            }
+        }
+    }
+
+    /// For every spot, the spots lying across it.
+    fn covered_by(self) -> Vec<Vec<usize>> {
+        let spots = self.spots();
+        spots
+            .iter()
+            .map(|under| {
+                (0..spots.len())
+                    .filter(|&i| {
+                        spots[i].layer == under.layer + 1
+                            && (spots[i].column - under.column).abs() <= 0.5
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Whether `card` can go on the waste with `top` showing.
+    fn plays_on(self, top: &Card, card: &Card) -> bool {
+        let (a, b) = (top.number, card.number);
+        match self {
+            Rules::Pyramid => false,
+            Rules::Golf => a + 1 == b || b + 1 == a,
+            Rules::TriPeaks => a + 1 == b || b + 1 == a || (a + b == 14 && (a == 1 || b == 1)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GameState {
+    pub rules: Rules,
+    /// A card or nothing in each of `Rules::spots`.
+    pub spots: Vec<Vec<PileCard>>,
+    /// For every spot, the spots lying across it.
+    pub covered_by: Vec<Vec<usize>>,
+    pub stock: Vec<PileCard>,
+    pub waste: Vec<PileCard>,
+    /// Where Pyramid's pairs and Kings go. Golf and TriPeaks play everything onto the waste.
+    pub foundation: Vec<PileCard>,
+    pub recycles: usize,
+}
+
+impl GameState {
+    /// Fills the spots in order, then Golf and TriPeaks turn one card off the stock to start the
+    /// waste.
+    pub fn deal(deck: Vec<Card>, rules: Rules) -> GameState {
+        let spots = rules.spots().len();
+        let mut deck = deck.into_iter();
+        let mut game = GameState {
+            rules,
+            spots: (&mut deck)
+                .take(spots)
+                .map(|card| vec![PileCard::down(card)])
+                .collect(),
+            covered_by: rules.covered_by(),
+            stock: Vec::new(),
+            waste: Vec::new(),
+            foundation: Vec::new(),
+            recycles: 0,
+        };
+        game.stock = deck.map(PileCard::down).collect();
+        if rules != Rules::Pyramid {
+            if let Some(card) = game.stock.pop() {
+                game.waste.push(PileCard::up(card.card));
+            }
+        }
+        game.turn_over_uncovered();
+        game
+    }
+
+    /// Nothing lies across `spot` any more.
+    pub fn is_uncovered(&self, spot: usize) -> bool {
+        self.covered_by[spot]
+            .iter()
+            .all(|&above| self.spots[above].is_empty())
+    }
+
+    /// Turns spots face up once they're uncovered and back down if they get covered again. Only
+    /// TriPeaks hides anything, the others are dealt face up. True if anything turned up.
+    fn turn_over_uncovered(&mut self) -> bool {
+        let hides = self.rules == Rules::TriPeaks;
+        let showing: Vec<bool> = (0..self.spots.len())
+            .map(|i| !hides || self.is_uncovered(i))
+            .collect();
+        let mut turned_up = false;
+        for (spot, show) in self.spots.iter_mut().zip(showing) {
+            if let Some(card) = spot.last_mut() {
+                turned_up |= show && !card.face_up;
+                card.face_up = show;
+            }
+        }
+        turned_up
+    }
+
+    /// Pyramid's moves onto anything but the foundation take two cards off together.
+    fn is_pair(&self, to: PileId) -> bool {
+        self.rules == Rules::Pyramid && matches!(to, PileId::Spot(_) | PileId::Waste)
+    }
+}
+
+impl super::Rules for GameState {
+    fn piles(&self) -> Vec<PileId> {
+        let mut piles = vec![PileId::Stock, PileId::Waste];
+        if self.rules == Rules::Pyramid {
+            piles.push(PileId::Foundation(0));
+        }
+        piles.extend((0..self.spots.len()).map(PileId::Spot));
+        piles
+    }
+
+    fn pile(&self, id: PileId) -> Option<&Vec<PileCard>> {
+        match id {
+            PileId::Stock => Some(&self.stock),
+            PileId::Waste => Some(&self.waste),
+            PileId::Foundation(0) if self.rules == Rules::Pyramid => Some(&self.foundation),
+            PileId::Spot(i) => self.spots.get(i),
+            _ => None,
+        }
+    }
+
+    fn pile_mut(&mut self, id: PileId) -> Option<&mut Vec<PileCard>> {
+        match id {
+            PileId::Stock => Some(&mut self.stock),
+            PileId::Waste => Some(&mut self.waste),
+            PileId::Foundation(0) if self.rules == Rules::Pyramid => Some(&mut self.foundation),
+            PileId::Spot(i) => self.spots.get_mut(i),
+            _ => None,
+        }
+    }
+
+    fn check_stock(&self, mv: Move) -> Result<(), IllegalMove> {
+        if mv == Move::Draw {
+            if self.stock.is_empty() {
+                return Err(IllegalMove::EmptyPile(PileId::Stock));
+            }
+            return Ok(());
+        }
+        if self.rules != Rules::Pyramid {
+            return Err(IllegalMove::NotAllowed);
+        }
+        if !self.stock.is_empty() {
+            return Err(IllegalMove::StockNotEmpty);
+        }
+        if self.waste.is_empty() {
+            return Err(IllegalMove::EmptyPile(PileId::Waste));
+        }
+        if self.recycles + 1 >= PYRAMID_PASSES {
+            return Err(IllegalMove::NoPassesLeft);
+        }
+        Ok(())
+    }
+
+    fn turn_stock(&mut self, mv: Move) -> Applied {
+        if mv == Move::Draw {
+            let card = self.stock.pop().expect("checked above");
+            self.waste.push(PileCard::up(card.card));
+            return Applied::new(mv, 1, false);
+        }
+        let count = self.waste.len();
+        self.stock = self
+            .waste
+            .drain(..)
+            .rev()
+            .map(|c| PileCard::down(c.card))
+            .collect();
+        self.recycles += 1;
+        Applied::new(mv, count, false)
+    }
+
+    fn undo_stock(&mut self, applied: &Applied) {
+        if applied.mv == Move::Draw {
+            let card = self
+                .waste
+                .pop()
+                .expect("undoing a draw that never happened");
+            self.stock.push(PileCard::down(card.card));
+            return;
+        }
+        self.waste = self
+            .stock
+            .drain(..)
+            .rev()
+            .map(|c| PileCard::up(c.card))
+            .collect();
+        self.recycles -= 1;
+    }
+
+    /// The one card `from` has to offer.
+    fn picked_up(&self, from: PileId, count: usize) -> Result<&[PileCard], IllegalMove> {
+        if count != 1 {
+            return Err(IllegalMove::NotASequence);
+        }
+        let pile = match from {
+            PileId::Spot(i) => {
+                let spot = self.spots.get(i).ok_or(IllegalMove::UnknownPile(from))?;
+                if spot.is_empty() {
+                    return Err(IllegalMove::EmptyPile(from));
+                }
+                if !self.is_uncovered(i) {
+                    return Err(IllegalMove::Covered);
+                }
+                spot
+            }
+            // only Pyramid takes cards back off the waste, to pair them
+            PileId::Waste if self.rules == Rules::Pyramid => &self.waste,
+            _ => return Err(IllegalMove::NotAllowed),
+        };
+        let top = pile
+            .len()
+            .checked_sub(1)
+            .ok_or(IllegalMove::EmptyPile(from))?;
+        Ok(&pile[top..])
+    }
+
+    fn fits(
+        &self,
+        moving: &[PileCard],
+        to: PileId,
+        _onto: &[PileCard],
+    ) -> Result<bool, IllegalMove> {
+        let card = &moving[0].card;
+        match (self.rules, to) {
+            (Rules::Pyramid, PileId::Foundation(0)) => Ok(card.number == 13),
+            (Rules::Pyramid, PileId::Spot(_) | PileId::Waste) => {
+                let other = &self.picked_up(to, 1)?[0].card;
+                Ok(card.number + other.number == 13)
+            }
+            (_, PileId::Waste) => Ok(match self.waste.last() {
+                Some(top) => self.rules.plays_on(&top.card, card),
+                None => true,
+            }),
+            _ => Err(IllegalMove::NotAllowed),
+        }
+    }
+
+    /// Cards come off one at a time, or as a pair in Pyramid, and turning up whatever they
+    /// uncovered.
+    fn transfer(&mut self, from: PileId, to: PileId, count: usize) -> Applied {
+        let moved = if self.is_pair(to) {
+            let card = self.pile_mut(from).and_then(|p| p.pop());
+            let other = self.pile_mut(to).and_then(|p| p.pop());
+            // the one it was put on goes underneath
+            self.foundation
+                .extend(other.into_iter().chain(card).map(|c| PileCard::up(c.card)));
+            2
+        } else {
+            let card = self
+                .pile_mut(from)
+                .and_then(|p| p.pop())
+                .expect("checked above");
+            self.pile_mut(to)
+                .expect("checked above")
+                .push(PileCard::up(card.card));
+            1
+        };
+        let flipped = self.turn_over_uncovered();
+        Applied::new(Move::Transfer { from, to, count }, moved, flipped)
+    }
+
+    fn undo_transfer(&mut self, applied: &Applied) {
+        let Move::Transfer { from, to, .. } = applied.mv else {
+            return;
+        };
+        let (card, other) = if self.is_pair(to) {
+            let card = self.foundation.pop();
+            (card, self.foundation.pop())
+        } else {
+            (self.pile_mut(to).and_then(|p| p.pop()), None)
+        };
+        let put_back = |pile: PileId, card: Option<PileCard>, game: &mut GameState| {
+            let card = card.expect("undoing a move that was never legal");
+            game.pile_mut(pile)
+                .expect("undoing a move that was never legal")
+                .push(card);
+        };
+        put_back(from, card, self);
+        if other.is_some() {
+            put_back(to, other, self);
+        }
+        self.turn_over_uncovered();
+    }
+
+    /// Won once the spots are cleared, whatever's left in the stock.
+    fn is_won(&self) -> bool {
+        self.spots.iter().all(|s| s.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::test_cards::{card, up};
+    use crate::rules::{new_deck, CardSuit, Rules as _};
+
+    /// A Pyramid deal with the spots cleared off, for laying out just the cards a test needs.
+    fn empty_pyramid() -> GameState {
+        let mut game = GameState::deal(new_deck(), Rules::Pyramid);
+        game.spots.iter_mut().for_each(Vec::clear);
+        game
+    }
+
+    fn transfer(from: PileId, to: PileId) -> Move {
+        Move::Transfer { from, to, count: 1 }
+    }
+
+    #[test]
+    fn spots_are_covered_by_the_ones_half_a_card_either_side_on_the_next_layer() {
+        let pyramid = Rules::Pyramid.covered_by();
+        assert_eq!(pyramid[0], vec![1, 2]);
+        assert_eq!(pyramid[1], vec![3, 4]);
+        assert_eq!(pyramid[4], vec![7, 8]);
+        assert!(pyramid[21..].iter().all(Vec::is_empty));
+        // Golf's rows are straight on top of each other
+        let golf = Rules::Golf.covered_by();
+        assert_eq!(golf[0], vec![7]);
+        assert_eq!(golf[13], vec![20]);
+        assert!(golf[28..].iter().all(Vec::is_empty));
+        // TriPeaks' first peak, its left shoulder, and the end of the third row
+        let tri_peaks = Rules::TriPeaks.covered_by();
+        assert_eq!(tri_peaks[0], vec![3, 4]);
+        assert_eq!(tri_peaks[3], vec![9, 10]);
+        assert_eq!(tri_peaks[17], vec![26, 27]);
+        assert!(tri_peaks[18..].iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn a_covered_card_cant_be_played() {
+        let mut game = empty_pyramid();
+        game.spots[0] = vec![up(13, CardSuit::Hearts)];
+        game.spots[2] = vec![up(5, CardSuit::Hearts)];
+        let to_foundation = transfer(PileId::Spot(0), PileId::Foundation(0));
+        assert_eq!(game.check(to_foundation), Err(IllegalMove::Covered));
+        game.spots[2].clear();
+        assert_eq!(game.check(to_foundation), Ok(()));
+    }
+
+    #[test]
+    fn pyramid_pairs_add_up_to_13_and_come_back_on_undo() {
+        let mut game = empty_pyramid();
+        game.spots[21] = vec![up(6, CardSuit::Hearts)];
+        game.spots[22] = vec![up(8, CardSuit::Spades)];
+        game.spots[23] = vec![up(7, CardSuit::Clubs)];
+        assert_eq!(
+            game.check(transfer(PileId::Spot(21), PileId::Spot(22))),
+            Err(IllegalMove::DoesNotFit)
+        );
+        let before = game.clone();
+
+        let applied = game
+            .apply(transfer(PileId::Spot(23), PileId::Spot(21)))
+            .unwrap();
+        assert_eq!(applied.count, 2);
+        assert!(game.spots[21].is_empty() && game.spots[23].is_empty());
+        // the one it was put on goes underneath
+        let cards: Vec<Card> = game.foundation.iter().map(|c| c.card).collect();
+        assert_eq!(
+            cards,
+            vec![card(6, CardSuit::Hearts), card(7, CardSuit::Clubs)]
+        );
+
+        game.undo(&applied);
+        assert_eq!(game, before);
+    }
+
+    #[test]
+    fn pyramid_goes_through_the_stock_three_times() {
+        let mut game = empty_pyramid();
+        game.stock.truncate(2);
+        for pass in 1..PYRAMID_PASSES {
+            assert_eq!(game.check(Move::Recycle), Err(IllegalMove::StockNotEmpty));
+            game.apply(Move::Draw).unwrap();
+            game.apply(Move::Draw).unwrap();
+            game.apply(Move::Recycle).unwrap();
+            assert_eq!(game.recycles, pass);
+        }
+        game.apply(Move::Draw).unwrap();
+        game.apply(Move::Draw).unwrap();
+        assert_eq!(game.check(Move::Recycle), Err(IllegalMove::NoPassesLeft));
+    }
+
+    #[test]
+    fn tri_peaks_goes_round_the_corner_and_golf_doesnt() {
+        let king = card(13, CardSuit::Spades);
+        let ace = card(1, CardSuit::Hearts);
+        let queen = card(12, CardSuit::Hearts);
+        assert!(Rules::TriPeaks.plays_on(&king, &ace));
+        assert!(Rules::TriPeaks.plays_on(&ace, &king));
+        assert!(Rules::TriPeaks.plays_on(&king, &queen));
+        assert!(!Rules::Golf.plays_on(&king, &ace));
+        assert!(!Rules::Golf.plays_on(&ace, &king));
+        assert!(Rules::Golf.plays_on(&king, &queen));
+    }
+}
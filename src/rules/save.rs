@@ -0,0 +1,201 @@
+//! The save file format. Written as RON so a broken save can at least be read by a person.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{history::History, scoring::ScoreSettings, shuffle::DealSeed, variant::VariantState};
+
+/// Bump this whenever `SaveFile` changes shape, older files get refused instead of half loaded.
+pub const SAVE_VERSION: u32 = 4;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub deal: DealSeed,
+    pub state: VariantState,
+    pub history: History,
+    pub scoring: ScoreSettings,
+    pub elapsed_secs: f32,
+}
+
+/// Just enough of a save to check its version before trying to read the rest.
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Corrupt(String),
+    IncompatibleVersion { found: u32, expected: u32 },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "couldn't read or write the save: {}", e),
+            SaveError::Corrupt(e) => write!(f, "the save file is corrupt: {}", e),
+            SaveError::IncompatibleVersion { found, expected } => write!(
+                f,
+                "the save file is version {}, this build only reads version {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> SaveError {
+        SaveError::Io(e)
+    }
+}
+
+/// Where the game keeps its save, under the user's data directory.
+pub fn save_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("solitaire").join("save.ron"))
+}
+
+pub fn parse(text: &str) -> Result<SaveFile, SaveError> {
+    let header: SaveHeader = ron::from_str(text).map_err(|e| SaveError::Corrupt(e.to_string()))?;
+    if header.version != SAVE_VERSION {
+        return Err(SaveError::IncompatibleVersion {
+            found: header.version,
+            expected: SAVE_VERSION,
+        });
+    }
+    ron::from_str(text).map_err(|e| SaveError::Corrupt(e.to_string()))
+}
+
+/// `Ok(None)` when there's simply no save yet.
+pub fn read(path: &Path) -> Result<Option<SaveFile>, SaveError> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse(&text).map(Some),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn write(path: &Path, save: &SaveFile) -> Result<(), SaveError> {
+    write_ron(path, save)
+}
+
+/// Money carried over between Vegas games, kept apart from the save so starting over doesn't
+/// lose it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bankroll {
+    /// Everything won or lost so far, the game being played included.
+    pub banked: i32,
+}
+
+pub fn bankroll_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("solitaire").join("bankroll.ron"))
+}
+
+/// An empty bankroll when there's no file yet.
+pub fn read_bankroll(path: &Path) -> Result<Bankroll, SaveError> {
+    match fs::read_to_string(path) {
+        Ok(text) => ron::from_str(&text).map_err(|e| SaveError::Corrupt(e.to_string())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Bankroll::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn write_bankroll(path: &Path, bankroll: &Bankroll) -> Result<(), SaveError> {
+    write_ron(path, bankroll)
+}
+
+/// Writes next to the real file first and renames over it, so quitting halfway through a write
+/// can't leave a truncated save behind.
+fn write_ron<T: Serialize>(path: &Path, value: &T) -> Result<(), SaveError> {
+    let text = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|e| SaveError::Corrupt(e.to_string()))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension("ron.tmp");
+    fs::write(&temp, text)?;
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::scoring::Scoring;
+    use crate::rules::variant::Variant;
+
+    /// Klondike #11 with one move played.
+    fn save_file() -> SaveFile {
+        let deal = DealSeed::Number(11);
+        let mut state = Variant::Klondike(Default::default()).deal(deal);
+        let mut history = History::default();
+        let mv = state.legal_moves()[0];
+        history.record(state.apply(mv).unwrap());
+        SaveFile {
+            version: SAVE_VERSION,
+            deal,
+            state,
+            history,
+            scoring: ScoreSettings {
+                scoring: Scoring::Vegas,
+                cumulative: true,
+            },
+            elapsed_secs: 42.5,
+        }
+    }
+
+    /// A file of its own under the temp directory, so tests running at once don't trip over each
+    /// other.
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("solitaire-test-{}", std::process::id()))
+            .join(name)
+    }
+
+    #[test]
+    fn a_written_save_reads_back_the_same() {
+        let path = temp_path("save.ron");
+        let save = save_file();
+        write(&path, &save).unwrap();
+        assert_eq!(read(&path).unwrap(), Some(save));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read(&path).unwrap(), None);
+    }
+
+    #[test]
+    fn a_truncated_save_is_corrupt() {
+        let text = ron::to_string(&save_file()).unwrap();
+        let cut = &text[..text.len() / 2];
+        assert!(matches!(parse(cut), Err(SaveError::Corrupt(_))));
+    }
+
+    #[test]
+    fn another_version_is_refused() {
+        let text = ron::to_string(&save_file()).unwrap();
+        let old = text.replacen(
+            &format!("version:{}", SAVE_VERSION),
+            &format!("version:{}", SAVE_VERSION - 1),
+            1,
+        );
+        assert_ne!(old, text);
+        assert!(matches!(
+            parse(&old),
+            Err(SaveError::IncompatibleVersion { found, expected })
+                if found == SAVE_VERSION - 1 && expected == SAVE_VERSION
+        ));
+    }
+
+    #[test]
+    fn the_bankroll_reads_back_and_starts_empty() {
+        let path = temp_path("bankroll.ron");
+        assert_eq!(read_bankroll(&path).unwrap(), Bankroll::default());
+        write_bankroll(&path, &Bankroll { banked: -37 }).unwrap();
+        assert_eq!(read_bankroll(&path).unwrap().banked, -37);
+        fs::remove_file(&path).unwrap();
+    }
+}
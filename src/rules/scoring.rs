@@ -0,0 +1,88 @@
+//! What moves are worth. Every scheme scores an `Applied` on its own, so the same number comes
+//! back off when it's undone, and anything that depends on time or the deal gets asked for
+//! separately.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Applied, Move, PileId};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Scoring {
+    /// The Windows scoring, points for getting cards out and a little off every ten seconds.
+    #[default]
+    Standard,
+    /// Pay $52 for the deal, win $5 back for every card on the foundations.
+    Vegas,
+}
+
+/// How a game gets scored, kept with the save so a restored game carries on the way it started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreSettings {
+    pub scoring: Scoring,
+    /// Carry Vegas money over from one game to the next, and from one run to the next.
+    pub cumulative: bool,
+}
+
+/// Standard scoring takes this much off every `TIME_PENALTY_SECS`.
+const TIME_PENALTY: i32 = 2;
+const TIME_PENALTY_SECS: f32 = 10.0;
+
+impl Scoring {
+    /// What the score starts at when the cards are dealt.
+    pub fn deal_points(self) -> i32 {
+        match self {
+            Scoring::Standard => 0,
+            Scoring::Vegas => -52,
+        }
+    }
+
+    pub fn points(self, applied: &Applied) -> i32 {
+        let Move::Transfer { from, to, .. } = applied.mv else {
+            return 0;
+        };
+        let onto_foundation = matches!(to, PileId::Foundation(_));
+        let off_foundation = matches!(from, PileId::Foundation(_));
+        match self {
+            Scoring::Standard => {
+                let moved = match (from, to) {
+                    (
+                        PileId::Waste | PileId::Tableau(_) | PileId::FreeCell(_),
+                        PileId::Foundation(_),
+                    ) => 10,
+                    (PileId::Waste, PileId::Tableau(_)) => 5,
+                    (PileId::Foundation(_), PileId::Tableau(_)) => -15,
+                    _ => 0,
+                };
+                let flipped = if applied.flipped { 5 } else { 0 };
+                moved + flipped
+            }
+            Scoring::Vegas => match (off_foundation, onto_foundation) {
+                (false, true) => 5,
+                (true, false) => -5,
+                _ => 0,
+            },
+        }
+    }
+
+    pub fn time_penalty(self, elapsed_secs: f32) -> i32 {
+        match self {
+            Scoring::Standard => TIME_PENALTY * (elapsed_secs / TIME_PENALTY_SECS) as i32,
+            Scoring::Vegas => 0,
+        }
+    }
+
+    /// The score to show, given the points from the deal and every move so far. Standard scores
+    /// never go below zero, Vegas ones are money and can.
+    pub fn total(self, points: i32, elapsed_secs: f32) -> i32 {
+        let total = points - self.time_penalty(elapsed_secs);
+        match self {
+            Scoring::Standard => total.max(0),
+            Scoring::Vegas => total,
+        }
+    }
+
+    /// The points for a game that got as far as `played`, deal included.
+    pub fn points_for<'a>(self, played: impl IntoIterator<Item = &'a Applied>) -> i32 {
+        self.deal_points() + played.into_iter().map(|a| self.points(a)).sum::<i32>()
+    }
+}
@@ -0,0 +1,202 @@
+//! Deck shuffles that come out the same everywhere. Nothing in here goes through `rand`'s
+//! generators, their output is allowed to change between versions and platforms and a deal
+//! number has to mean the same cards forever.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::Card;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DealSeed {
+    /// A FreeCell style deal number, shuffled with the old Microsoft generator.
+    Number(u32),
+    /// Any 64 bit seed.
+    Seed(u64),
+}
+
+impl DealSeed {
+    pub fn random() -> DealSeed {
+        DealSeed::Seed(rand::random())
+    }
+
+    /// One of the 32000 numbered deals Windows FreeCell came with.
+    pub fn random_classic() -> DealSeed {
+        DealSeed::Number(rand::random::<u32>() % 32000 + 1)
+    }
+
+    /// A shuffled 52 card deck.
+    pub fn deck(self) -> Vec<Card> {
+        match self {
+            DealSeed::Number(n) => microsoft_deck(n),
+            DealSeed::Seed(s) => seeded_deck(s),
+        }
+    }
+
+    /// Shuffles any set of cards. Deal numbers only mean the Microsoft shuffle for a single deck,
+    /// anything else gets the number used as a plain seed.
+    pub fn shuffle(self, cards: Vec<Card>) -> Vec<Card> {
+        match self {
+            DealSeed::Number(n) => seeded_shuffle(n as u64, cards),
+            DealSeed::Seed(s) => seeded_shuffle(s, cards),
+        }
+    }
+}
+
+impl fmt::Display for DealSeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DealSeed::Number(n) => write!(f, "#{}", n),
+            DealSeed::Seed(s) => write!(f, "{:#018x}", s),
+        }
+    }
+}
+
+/// Reads back what `Display` prints, `#12345` for a deal number and `0x...` or plain digits for
+/// a seed.
+impl FromStr for DealSeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<DealSeed, String> {
+        let s = s.trim();
+        if let Some(n) = s.strip_prefix('#') {
+            return n
+                .parse()
+                .map(DealSeed::Number)
+                .map_err(|e| format!("bad deal number {:?}: {}", s, e));
+        }
+        let seed = match s.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => s.parse(),
+        };
+        seed.map(DealSeed::Seed)
+            .map_err(|e| format!("bad seed {:?}: {}", s, e))
+    }
+}
+
+/// Microsoft's deals number their cards rank first with suits in clubs, diamonds, hearts, spades
+/// order. cards.png has rows of hearts, spades, diamonds, clubs.
+fn microsoft_card(n: usize) -> Card {
+    let rank = n / 4;
+    let row = [3, 2, 0, 1][n % 4];
+    Card::from_index(row * 13 + rank)
+}
+
+/// The shuffle behind the Windows FreeCell deal numbers, so deal #1 here is deal #1 there. The
+/// first card of the result is the first one dealt.
+pub fn microsoft_deck(deal: u32) -> Vec<Card> {
+    let mut state = deal;
+    let mut next = || {
+        state = state.wrapping_mul(214013).wrapping_add(2531011) & 0x7fff_ffff;
+        (state >> 16) as usize
+    };
+    let mut cards: Vec<usize> = (0..52).rev().collect();
+    for i in 0..52 {
+        let j = 51 - next() % (52 - i);
+        cards.swap(i, j);
+    }
+    cards.into_iter().map(microsoft_card).collect()
+}
+
+pub fn seeded_deck(seed: u64) -> Vec<Card> {
+    seeded_shuffle(seed, super::new_deck())
+}
+
+/// Fisher-Yates driven by splitmix64.
+pub fn seeded_shuffle(seed: u64, mut deck: Vec<Card>) -> Vec<Card> {
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    for i in (1..deck.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        deck.swap(i, j);
+    }
+    deck
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::CardSuit;
+
+    /// The way the published deal lists write cards, `JD`, `TS` and so on.
+    fn names(cards: &[Card]) -> Vec<String> {
+        cards
+            .iter()
+            .map(|c| {
+                let rank = "A23456789TJQK".as_bytes()[c.number - 1] as char;
+                let suit = match c.suit {
+                    CardSuit::Hearts => 'H',
+                    CardSuit::Spades => 'S',
+                    CardSuit::Diamonds => 'D',
+                    CardSuit::Clubs => 'C',
+                };
+                format!("{}{}", rank, suit)
+            })
+            .collect()
+    }
+
+    /// Saved games and shared seeds are only worth anything while these stay the same.
+    #[test]
+    fn seeds_always_deal_the_same_cards() {
+        let firsts = [
+            (0, "8C JD 9H 2C 2S QS 6C TH"),
+            (1, "7S 9H 4S 8D 8S 5C JS 6S"),
+            (0xdead_beef, "KD 4H 6H QC TC 5C 5D 9H"),
+            (u64::MAX, "JH KC 7C 4H AS JD 5S 2D"),
+        ];
+        for (seed, first) in firsts {
+            let deck = DealSeed::Seed(seed).deck();
+            assert_eq!(names(&deck[..8]).join(" "), first, "seed {:#x}", seed);
+        }
+    }
+
+    #[test]
+    fn seeded_deck_has_every_card_once() {
+        let mut deck = seeded_deck(42);
+        deck.sort_by_key(|c| c.index);
+        assert_eq!(deck, crate::rules::new_deck());
+    }
+
+    /// Checked against the layouts published for Windows FreeCell, dealt row by row.
+    #[test]
+    fn numbered_deals_match_windows() {
+        let deals = [
+            (
+                1,
+                "JD 2D 9H JC 5D 7H 7C 5H KD KC 9S 5S AD QC KH 3H 2S KS 9D QD JS AS AH 3C 4C 5C \
+                 TS QH 4H AC 4D 7S 3S TD 4S TH 8H 2C JH 7D 6D 8S 8D QS 6C 3D 8C TC 6S 9C 2H 6H",
+            ),
+            (
+                617,
+                "7D AD 5C 3S 5S 8C 2D AH TD 7S QD AC 6D 8H AS KH TH QC 3H 9D 6S 8D 3D TC KD 5H \
+                 9S 3C 8S 7H 4D JS 4C QS 9C 9H 7C 6H 2C 2S 4S TS 2H 5D JC 6C JH QH JD KS KC 4H",
+            ),
+            // the one deal Windows can't win
+            (
+                11982,
+                "AH AS 4H AC 2D 6S TS JS 3D 3H QS QC 8S 7H AD KS KD 6H 5S 4D 9H JH 9S 3C JC 5D \
+                 5C 8C 9D TD KH 7C 6C 2C TH QH 6D TC 4S 7S JD 7D 8H 9C 2H QD 4C 5H KC 8D 2S 3S",
+            ),
+        ];
+        for (deal, layout) in deals {
+            assert_eq!(
+                names(&DealSeed::Number(deal).deck()).join(" "),
+                layout,
+                "#{}",
+                deal
+            );
+        }
+        // and the first column comes out the way it does on Windows
+        let game = crate::rules::freecell::GameState::deal(DealSeed::Number(1).deck());
+        let cards: Vec<Card> = game.tableau[0].iter().map(|c| c.card).collect();
+        assert_eq!(names(&cards).join(" "), "JD KD 2S 4C 3S 6D 6S");
+    }
+}
@@ -0,0 +1,384 @@
+//! Depth first search for a way to win a deal, over the same `GameState` the game plays on.
+//! Positions already searched are remembered by hash so the stock going round and cards
+//! shuffling between columns don't get explored twice. Only moves that look like they could help
+//! get tried, so finding a win proves a deal can be won but running out doesn't prove it can't.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+use super::klondike::{GameState, StockRules, FOUNDATIONS, TABLEAU};
+use super::shuffle::DealSeed;
+use super::{
+    builds_down_alternating, builds_up_in_suit, Applied, Card, Move, PileCard, PileId, Rules,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Play these from the deal and it's won.
+    Solvable(Vec<Move>),
+    /// Every move the search thinks could help was tried from every position it reached, and
+    /// none of them won. It passes over moves it doesn't expect to help and knows positions by a
+    /// hash that can collide, so the deal might still be winnable.
+    NoSolutionFound,
+    /// Ran out of nodes before finding out either way.
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    pub verdict: Verdict,
+    /// Positions searched.
+    pub nodes: usize,
+}
+
+pub fn solve_deal(deal: DealSeed, stock_rules: StockRules, budget: usize) -> Report {
+    solve(&GameState::deal(deal.deck(), stock_rules), budget)
+}
+
+/// Looks at no more than `budget` positions.
+pub fn solve(game: &GameState, budget: usize) -> Report {
+    let mut search = Search {
+        state: game.clone(),
+        seen: HashSet::new(),
+        path: Vec::new(),
+        nodes: 0,
+        budget,
+    };
+    let verdict = match search.dfs() {
+        Outcome::Won => Verdict::Solvable(search.path.iter().map(|a| a.mv).collect()),
+        Outcome::DeadEnd => Verdict::NoSolutionFound,
+        Outcome::OutOfBudget => Verdict::Unknown,
+    };
+    Report {
+        verdict,
+        nodes: search.nodes,
+    }
+}
+
+enum Outcome {
+    Won,
+    DeadEnd,
+    OutOfBudget,
+}
+
+/// Turn the stock over `turns` times, drawing or recycling whichever it allows, then play `mv`.
+#[derive(Clone, Copy, Debug)]
+struct Play {
+    turns: usize,
+    mv: Move,
+}
+
+impl Play {
+    fn single(mv: Move) -> Play {
+        Play { turns: 0, mv }
+    }
+}
+
+/// A position on the way down, with the plays still to try from it.
+struct Frame {
+    plays: Vec<Play>,
+    next: usize,
+    depth: usize,
+}
+
+struct Search {
+    state: GameState,
+    seen: HashSet<u64>,
+    path: Vec<Applied>,
+    nodes: usize,
+    budget: usize,
+}
+
+impl Search {
+    /// Keeps its own stack, a long game is deeper than the thread's.
+    fn dfs(&mut self) -> Outcome {
+        let mut stack = Vec::new();
+        if let Some(outcome) = self.visit(&mut stack) {
+            return outcome;
+        }
+        while let Some(frame) = stack.last_mut() {
+            while self.path.len() > frame.depth {
+                let applied = self.path.pop().expect("still above depth");
+                self.state.undo(&applied);
+            }
+            let Some(&play) = frame.plays.get(frame.next) else {
+                stack.pop();
+                continue;
+            };
+            frame.next += 1;
+            self.play(play);
+            if let Some(outcome) = self.visit(&mut stack) {
+                return outcome;
+            }
+        }
+        Outcome::DeadEnd
+    }
+
+    /// Looks at the current position, pushing it if it's worth searching from.
+    fn visit(&mut self, stack: &mut Vec<Frame>) -> Option<Outcome> {
+        if self.state.is_won() {
+            return Some(Outcome::Won);
+        }
+        if !self.seen.insert(position_key(&self.state)) {
+            return None;
+        }
+        if self.nodes >= self.budget {
+            return Some(Outcome::OutOfBudget);
+        }
+        self.nodes += 1;
+        stack.push(Frame {
+            plays: candidate_plays(&self.state),
+            next: 0,
+            depth: self.path.len(),
+        });
+        None
+    }
+
+    fn play(&mut self, play: Play) {
+        let turns = (0..play.turns).map(|_| Move::Draw);
+        for mv in turns.chain([play.mv]) {
+            let mv = if mv == Move::Draw && self.state.stock.is_empty() {
+                Move::Recycle
+            } else {
+                mv
+            };
+            let applied = self
+                .state
+                .apply(mv)
+                .expect("candidate_plays only hands out legal moves");
+            self.path.push(applied);
+        }
+    }
+}
+
+/// Columns and foundations are interchangeable, so positions that only differ in which column
+/// is which hash the same. The pass count only matters when passes run out.
+fn position_key(state: &GameState) -> u64 {
+    let pile_bytes = |pile: &Vec<PileCard>| -> Vec<u8> {
+        pile.iter()
+            .map(|c| c.card.index as u8 | if c.face_up { 0x80 } else { 0 })
+            .collect()
+    };
+    let mut columns: Vec<Vec<u8>> = state.tableau.iter().map(pile_bytes).collect();
+    columns.sort();
+    let mut foundations: Vec<Vec<u8>> = state.foundations.iter().map(pile_bytes).collect();
+    foundations.sort();
+    let mut hasher = DefaultHasher::new();
+    pile_bytes(&state.stock).hash(&mut hasher);
+    pile_bytes(&state.waste).hash(&mut hasher);
+    foundations.hash(&mut hasher);
+    columns.hash(&mut hasher);
+    if state.stock_rules.max_passes.is_some() {
+        state.recycles.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// A card can go up without ever being missed when nothing left could want to sit on it, both
+/// cards of the other colour a rank down are already up.
+fn is_safe_to_foundation(state: &GameState, from: PileId) -> bool {
+    let Some(card) = state.pile(from).and_then(|p| p.last()) else {
+        return false;
+    };
+    card.card.number <= 2
+        || state
+            .foundations
+            .iter()
+            .filter_map(|f| f.last())
+            .filter(|top| {
+                top.card.color != card.card.color && top.card.number + 1 >= card.card.number
+            })
+            .count()
+            == 2
+}
+
+/// The plays worth trying from here, most promising first. Each is a few moves played as one,
+/// turning the stock over until a card comes up and then playing it counts as a single play so
+/// the search doesn't wander through every way of leaving the stock. A safe move up to the
+/// foundations is played on its own without branching, and moves that can only shuffle cards
+/// around without opening anything up are left out.
+fn candidate_plays(state: &GameState) -> Vec<Play> {
+    let mut plays: Vec<(i32, Play)> = Vec::new();
+    // only the first empty column, they're all the same
+    let empty_column = state.tableau.iter().position(|c| c.is_empty());
+    let targets: Vec<PileId> = (0..FOUNDATIONS)
+        .map(PileId::Foundation)
+        .chain((0..TABLEAU).filter_map(|t| {
+            let empty = state.tableau[t].is_empty();
+            (!empty || Some(t) == empty_column).then_some(PileId::Tableau(t))
+        }))
+        .collect();
+    let sources = [(PileId::Waste, 1)]
+        .into_iter()
+        .chain((0..FOUNDATIONS).map(|f| (PileId::Foundation(f), 1)))
+        .chain((0..TABLEAU).map(|t| (PileId::Tableau(t), run_length(&state.tableau[t]))));
+    for (from, longest) in sources {
+        let source = state.pile(from).map_or(&[][..], |p| &p[..]);
+        for count in 1..=longest.min(source.len()) {
+            let left = source.len() - count;
+            let uncovers = left > 0 && !source[left - 1].face_up;
+            for &to in targets.iter() {
+                let mv = Move::Transfer { from, to, count };
+                if state.check(mv).is_err() {
+                    continue;
+                }
+                let priority = match (from, to) {
+                    // only there to swap aces and twos between empty foundations
+                    (PileId::Foundation(_), PileId::Foundation(_)) => continue,
+                    (_, PileId::Foundation(_)) => {
+                        if is_safe_to_foundation(state, from) {
+                            return vec![Play::single(mv)];
+                        }
+                        if uncovers {
+                            100
+                        } else {
+                            90
+                        }
+                    }
+                    (PileId::Tableau(_), PileId::Tableau(t)) => {
+                        if uncovers {
+                            80 + left as i32
+                        } else if left == 0 && !state.tableau[t].is_empty() {
+                            60
+                        } else if left > 0 && frees_for_foundation(state, &source[left - 1]) {
+                            // splitting a run is only worth it to get at the card under it
+                            20
+                        } else {
+                            continue;
+                        }
+                    }
+                    (PileId::Waste, _) => 50,
+                    // taking a card back down, worth it when it gives something a place to go
+                    (PileId::Foundation(_), PileId::Tableau(_)) => {
+                        let card = &source[source.len() - 1].card;
+                        if !gives_a_home(state, card) {
+                            continue;
+                        }
+                        0
+                    }
+                    _ => continue,
+                };
+                plays.push((priority, Play::single(mv)));
+            }
+        }
+    }
+    plays.extend(stock_plays(state, &targets));
+    plays.sort_by_key(|(priority, _)| -priority);
+    plays.into_iter().map(|(_, play)| play).collect()
+}
+
+/// Every card the stock can still turn up, with the draws and recycles it takes to get to it and
+/// the move that plays it. Goes round until the stock is back where it started, or for a second
+/// recycle, drawing three at a time from part way through doesn't always come back round to the
+/// same place but after one recycle everything it'll ever show has been seen.
+fn stock_plays(state: &GameState, targets: &[PileId]) -> Vec<(i32, Play)> {
+    let mut plays = Vec::new();
+    let mut talon = state.clone();
+    let mut turns = 0;
+    let mut recycled = false;
+    loop {
+        let turn = if talon.check(Move::Draw).is_ok() {
+            Move::Draw
+        } else if !recycled && talon.check(Move::Recycle).is_ok() {
+            recycled = true;
+            Move::Recycle
+        } else {
+            break;
+        };
+        talon.apply(turn).expect("just checked");
+        turns += 1;
+        let round_again =
+            talon.stock.len() == state.stock.len() && talon.waste.len() == state.waste.len();
+        if round_again {
+            break;
+        }
+        if turn == Move::Recycle {
+            continue;
+        }
+        for &to in targets {
+            let mv = Move::Transfer {
+                from: PileId::Waste,
+                to,
+                count: 1,
+            };
+            if talon.check(mv).is_err() {
+                continue;
+            }
+            let priority = match to {
+                PileId::Foundation(_) => 70,
+                _ => 40,
+            };
+            plays.push((priority, Play { turns, mv }));
+        }
+    }
+    plays
+}
+
+/// How many face up cards on top of a column are built down in alternating colours.
+fn run_length(column: &[PileCard]) -> usize {
+    let face_up = column.iter().rev().take_while(|c| c.face_up).count();
+    if face_up == 0 {
+        return 0;
+    }
+    let run = &column[column.len() - face_up..];
+    1 + run
+        .windows(2)
+        .rev()
+        .take_while(|w| builds_down_alternating(&w[0].card, &w[1].card))
+        .count()
+}
+
+fn frees_for_foundation(state: &GameState, card: &PileCard) -> bool {
+    state
+        .foundations
+        .iter()
+        .any(|f| builds_up_in_suit(f.last().map(|c| &c.card), &card.card))
+}
+
+/// Whether the waste card or the bottom of some face up run could go on `card`.
+fn gives_a_home(state: &GameState, card: &Card) -> bool {
+    let waste = state.waste.last().into_iter();
+    let run_bottoms = state.tableau.iter().filter_map(|column| {
+        let len = run_length(column);
+        column.len().checked_sub(len).and_then(|i| column.get(i))
+    });
+    waste
+        .chain(run_bottoms)
+        .any(|c| c.face_up && builds_down_alternating(card, &c.card))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solution_plays_through_to_a_win() {
+        let deal = DealSeed::Number(1);
+        let Verdict::Solvable(moves) = solve_deal(deal, StockRules::default(), 1_000).verdict
+        else {
+            panic!("#1 goes out");
+        };
+        let mut game = GameState::deal(deal.deck(), StockRules::default());
+        for mv in moves {
+            game.apply(mv).unwrap();
+        }
+        assert!(game.is_won());
+    }
+
+    /// Not a claim about these deals, only that the search keeps running out the same way on them.
+    #[test]
+    fn runs_out_of_moves_on_the_same_deals() {
+        for n in [32, 96] {
+            let report = solve_deal(DealSeed::Number(n), StockRules::default(), 1_000_000);
+            assert_eq!(report.verdict, Verdict::NoSolutionFound, "#{}", n);
+        }
+    }
+
+    #[test]
+    fn gives_up_once_the_budget_is_spent() {
+        let report = solve_deal(DealSeed::Number(2), StockRules::default(), 1_000);
+        assert_eq!(report.verdict, Verdict::Unknown);
+        assert!(report.nodes <= 1_000);
+    }
+}
@@ -0,0 +1,352 @@
+//! Spider, two decks, ten columns. Runs are built down regardless of suit but only move together
+//! when they're all one suit, and a finished King to Ace run of one suit gets lifted off onto the
+//! foundations by itself.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Applied, Card, IllegalMove, Move, PileCard, PileId, Rules};
+
+pub const FOUNDATIONS: usize = 8;
+pub const TABLEAU: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GameState {
+    pub stock: Vec<PileCard>,
+    /// Finished runs, Ace at the bottom so the King shows.
+    pub foundations: [Vec<PileCard>; FOUNDATIONS],
+    pub tableau: [Vec<PileCard>; TABLEAU],
+    /// 1, 2 or 4.
+    pub suits: usize,
+}
+
+/// The 104 cards for a game with `suits` suits, as many copies of each suit as it takes. Spades
+/// come first, then hearts, then the other two.
+pub fn deck(suits: usize) -> Vec<Card> {
+    // rows of cards.png
+    let rows: &[usize] = match suits {
+        1 => &[1],
+        2 => &[1, 0],
+        _ => &[1, 0, 2, 3],
+    };
+    let copies = 8 / rows.len();
+    (0..copies)
+        .flat_map(|copy| {
+            rows.iter().flat_map(move |row| {
+                (0..13).map(move |rank| Card::from_index(row * 13 + rank).in_deck(copy))
+            })
+        })
+        .collect()
+}
+
+/// `upper` can go on `lower`, any suit as long as it's one rank down.
+fn builds_down(lower: &Card, upper: &Card) -> bool {
+    upper.number + 1 == lower.number
+}
+
+/// `upper` can go on `lower` and they move together.
+fn builds_down_in_suit(lower: &Card, upper: &Card) -> bool {
+    builds_down(lower, upper) && upper.suit == lower.suit
+}
+
+impl GameState {
+    /// Deals 54 cards across the columns row by row, the first four columns get six and the rest
+    /// five, last one face up. The other 50 are the stock, dealt ten at a time.
+    pub fn deal(deck: Vec<Card>, suits: usize) -> GameState {
+        let mut deck = deck.into_iter();
+        let mut tableau: [Vec<PileCard>; TABLEAU] = Default::default();
+        for row in 0..6 {
+            for (col, pile) in tableau.iter_mut().enumerate() {
+                if row == 5 && col >= 4 {
+                    continue;
+                }
+                let card = deck
+                    .next()
+                    .expect("Not enough cards in the deck to deal Spider.");
+                pile.push(PileCard::down(card));
+            }
+        }
+        for pile in tableau.iter_mut() {
+            if let Some(top) = pile.last_mut() {
+                top.face_up = true;
+            }
+        }
+        GameState {
+            stock: deck.map(PileCard::down).collect(),
+            foundations: Default::default(),
+            tableau,
+            suits,
+        }
+    }
+
+    /// Lifts every finished King to Ace run off onto the next empty foundation.
+    fn clear_finished_runs(&mut self) -> (u16, u16) {
+        let mut cleared = 0;
+        let mut uncovered = 0;
+        for col in 0..TABLEAU {
+            let column = &self.tableau[col];
+            if column.len() < 13 {
+                continue;
+            }
+            let run = &column[column.len() - 13..];
+            let finished = run[0].card.number == 13
+                && run.iter().all(|c| c.face_up)
+                && run
+                    .windows(2)
+                    .all(|w| builds_down_in_suit(&w[0].card, &w[1].card));
+            if !finished {
+                continue;
+            }
+            let Some(foundation) = self.foundations.iter_mut().find(|f| f.is_empty()) else {
+                continue;
+            };
+            let column = &mut self.tableau[col];
+            let run = column.split_off(column.len() - 13);
+            foundation.extend(run.into_iter().rev());
+            cleared |= 1 << col;
+            if let Some(top) = column.last_mut() {
+                if !top.face_up {
+                    top.face_up = true;
+                    uncovered |= 1 << col;
+                }
+            }
+        }
+        (cleared, uncovered)
+    }
+}
+
+impl Rules for GameState {
+    fn piles(&self) -> Vec<PileId> {
+        [PileId::Stock]
+            .into_iter()
+            .chain((0..FOUNDATIONS).map(PileId::Foundation))
+            .chain((0..TABLEAU).map(PileId::Tableau))
+            .collect()
+    }
+
+    fn pile(&self, id: PileId) -> Option<&Vec<PileCard>> {
+        match id {
+            PileId::Stock => Some(&self.stock),
+            PileId::Foundation(i) => self.foundations.get(i),
+            PileId::Tableau(i) => self.tableau.get(i),
+            _ => None,
+        }
+    }
+
+    fn pile_mut(&mut self, id: PileId) -> Option<&mut Vec<PileCard>> {
+        match id {
+            PileId::Stock => Some(&mut self.stock),
+            PileId::Foundation(i) => self.foundations.get_mut(i),
+            PileId::Tableau(i) => self.tableau.get_mut(i),
+            _ => None,
+        }
+    }
+
+    fn check_stock(&self, mv: Move) -> Result<(), IllegalMove> {
+        if mv == Move::Recycle {
+            return Err(IllegalMove::NotAllowed);
+        }
+        if self.stock.is_empty() {
+            return Err(IllegalMove::EmptyPile(PileId::Stock));
+        }
+        // the usual rule, every column needs a card before the stock gets dealt
+        if self.tableau.iter().any(|c| c.is_empty()) {
+            return Err(IllegalMove::NotAllowed);
+        }
+        Ok(())
+    }
+
+    /// Deals a card face up onto every column.
+    fn turn_stock(&mut self, mv: Move) -> Applied {
+        for column in self.tableau.iter_mut() {
+            let mut card = self.stock.pop().expect("checked above");
+            card.face_up = true;
+            column.push(card);
+        }
+        Applied::new(mv, TABLEAU, false)
+    }
+
+    fn undo_stock(&mut self, _applied: &Applied) {
+        for column in self.tableau.iter_mut().rev() {
+            let card = column.pop().expect("undoing a deal that never happened");
+            self.stock.push(PileCard::down(card.card));
+        }
+    }
+
+    fn picked_up(&self, from: PileId, count: usize) -> Result<&[PileCard], IllegalMove> {
+        let PileId::Tableau(_) = from else {
+            return Err(IllegalMove::NotAllowed);
+        };
+        let moving = self.top_cards(from, count)?;
+        if moving
+            .windows(2)
+            .any(|w| !builds_down_in_suit(&w[0].card, &w[1].card))
+        {
+            return Err(IllegalMove::NotASequence);
+        }
+        Ok(moving)
+    }
+
+    fn fits(
+        &self,
+        moving: &[PileCard],
+        to: PileId,
+        onto: &[PileCard],
+    ) -> Result<bool, IllegalMove> {
+        let PileId::Tableau(_) = to else {
+            return Err(IllegalMove::NotAllowed);
+        };
+        Ok(match onto.last() {
+            None => true,
+            Some(top) => top.face_up && builds_down(&top.card, &moving[0].card),
+        })
+    }
+
+    fn settle(&mut self, applied: &mut Applied) {
+        (applied.cleared, applied.uncovered) = self.clear_finished_runs();
+    }
+
+    /// The runs went onto the foundations in column order, so they come back off in reverse.
+    fn unsettle(&mut self, applied: &Applied) {
+        for col in (0..TABLEAU).rev() {
+            if applied.cleared & (1 << col) == 0 {
+                continue;
+            }
+            let column = &mut self.tableau[col];
+            if applied.uncovered & (1 << col) != 0 {
+                if let Some(top) = column.last_mut() {
+                    top.face_up = false;
+                }
+            }
+            let foundation = self
+                .foundations
+                .iter_mut()
+                .rev()
+                .find(|f| !f.is_empty())
+                .expect("undoing a run that was never cleared");
+            column.extend(foundation.drain(..).rev());
+        }
+    }
+
+    fn is_won(&self) -> bool {
+        self.foundations.iter().all(|f| f.len() == 13)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::test_cards::{down, up};
+    use crate::rules::CardSuit;
+
+    /// Nothing anywhere, for laying out just the cards a test needs.
+    fn empty_table() -> GameState {
+        GameState {
+            stock: Vec::new(),
+            foundations: Default::default(),
+            tableau: Default::default(),
+            suits: 1,
+        }
+    }
+
+    /// King down to `lowest` in spades, all face up.
+    fn run_down_to(lowest: usize) -> Vec<PileCard> {
+        (lowest..=13)
+            .rev()
+            .map(|n| up(n, CardSuit::Spades))
+            .collect()
+    }
+
+    #[test]
+    fn every_deck_has_104_cards() {
+        for suits in [1, 2, 4] {
+            let deck = deck(suits);
+            assert_eq!(deck.len(), 104, "{} suits", suits);
+            let game = GameState::deal(deck, suits);
+            let on_table: usize = game.tableau.iter().map(Vec::len).sum();
+            assert_eq!((on_table, game.stock.len()), (54, 50));
+        }
+    }
+
+    #[test]
+    fn a_finished_run_gets_cleared_and_comes_back_on_undo() {
+        let mut game = empty_table();
+        game.tableau[0] = vec![down(5, CardSuit::Hearts)];
+        game.tableau[0].extend(run_down_to(2));
+        game.tableau[1] = vec![up(9, CardSuit::Hearts), up(1, CardSuit::Spades)];
+        let before = game.clone();
+
+        let applied = game
+            .apply(Move::Transfer {
+                from: PileId::Tableau(1),
+                to: PileId::Tableau(0),
+                count: 1,
+            })
+            .unwrap();
+        assert_eq!((applied.cleared, applied.uncovered), (1, 1));
+        assert_eq!(game.foundations[0].len(), 13);
+        // Ace at the bottom so the King shows
+        assert_eq!(game.foundations[0][12].card.number, 13);
+        assert_eq!(game.tableau[0], vec![up(5, CardSuit::Hearts)]);
+
+        game.undo(&applied);
+        assert_eq!(game, before);
+    }
+
+    #[test]
+    fn a_run_of_mixed_suits_stays_put() {
+        let mut game = empty_table();
+        game.tableau[0] = run_down_to(2);
+        game.tableau[1] = vec![up(1, CardSuit::Hearts)];
+        let applied = game
+            .apply(Move::Transfer {
+                from: PileId::Tableau(1),
+                to: PileId::Tableau(0),
+                count: 1,
+            })
+            .unwrap();
+        assert_eq!(applied.cleared, 0);
+        assert_eq!(game.tableau[0].len(), 13);
+        assert!(game.foundations.iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn the_stock_needs_a_card_in_every_column() {
+        let mut game = empty_table();
+        game.stock = (1..=10).map(|n| down(n, CardSuit::Spades)).collect();
+        for column in game.tableau.iter_mut().skip(1) {
+            column.push(up(13, CardSuit::Hearts));
+        }
+        assert_eq!(game.check(Move::Draw), Err(IllegalMove::NotAllowed));
+        game.tableau[0].push(up(13, CardSuit::Hearts));
+        let applied = game.apply(Move::Draw).unwrap();
+        assert_eq!(applied.count, TABLEAU);
+        assert!(game.stock.is_empty());
+        assert!(game.tableau.iter().all(|c| c.len() == 2 && c[1].face_up));
+        assert_eq!(
+            game.check(Move::Draw),
+            Err(IllegalMove::EmptyPile(PileId::Stock))
+        );
+    }
+
+    #[test]
+    fn undoing_a_deal_that_finished_a_run_puts_it_all_back() {
+        let mut game = empty_table();
+        game.tableau[0] = vec![down(5, CardSuit::Hearts)];
+        game.tableau[0].extend(run_down_to(2));
+        for column in game.tableau.iter_mut().skip(1) {
+            column.push(up(13, CardSuit::Hearts));
+        }
+        // the top of the stock goes to the first column
+        game.stock = (2..=10).map(|n| down(n, CardSuit::Hearts)).collect();
+        game.stock.push(down(1, CardSuit::Spades));
+        let before = game.clone();
+
+        let applied = game.apply(Move::Draw).unwrap();
+        assert_eq!((applied.cleared, applied.uncovered), (1, 1));
+        assert_eq!(game.foundations[0].len(), 13);
+        assert_eq!(game.tableau[0], vec![up(5, CardSuit::Hearts)]);
+
+        game.undo(&applied);
+        assert_eq!(game, before);
+    }
+}
@@ -0,0 +1,277 @@
+//! Which game is being played. Everything outside the rules goes through `VariantState`, so it
+//! doesn't need to know which one it is, and each variant says how its piles are laid out so the
+//! board can be built from that.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::klondike::{self, StockRules};
+use super::shuffle::DealSeed;
+use super::{
+    custom, freecell, overlap, spider, Applied, Card, IllegalMove, Move, PileCard, PileId, Rules,
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Variant {
+    Klondike(StockRules),
+    /// Played with 1, 2 or 4 suits.
+    Spider {
+        suits: usize,
+    },
+    FreeCell,
+    /// Pyramid, Golf or TriPeaks.
+    Overlap(overlap::Rules),
+    /// Loaded from a variant file.
+    Custom(Arc<custom::Definition>),
+}
+
+impl Default for Variant {
+    fn default() -> Variant {
+        Variant::Klondike(StockRules::default())
+    }
+}
+
+impl Variant {
+    pub fn deal(&self, seed: DealSeed) -> VariantState {
+        match *self {
+            Variant::Klondike(stock_rules) => {
+                VariantState::Klondike(klondike::GameState::deal(seed.deck(), stock_rules))
+            }
+            Variant::Spider { suits } => VariantState::Spider(spider::GameState::deal(
+                seed.shuffle(spider::deck(suits)),
+                suits,
+            )),
+            Variant::FreeCell => VariantState::FreeCell(freecell::GameState::deal(seed.deck())),
+            Variant::Overlap(rules) => {
+                VariantState::Overlap(overlap::GameState::deal(seed.deck(), rules))
+            }
+            Variant::Custom(ref definition) => {
+                let deck = match definition.decks {
+                    1 => seed.deck(),
+                    _ => seed.shuffle(definition.deck()),
+                };
+                VariantState::Custom(custom::GameState::deal(definition.clone(), deck))
+            }
+        }
+    }
+
+    pub fn layout(&self) -> Layout {
+        match *self {
+            Variant::Klondike(stock_rules) => {
+                let mut slots = vec![
+                    PileSlot::new(PileId::Stock, 0.0, 0.0, Fan::Stacked),
+                    PileSlot::new(
+                        PileId::Waste,
+                        1.0,
+                        0.0,
+                        Fan::Right {
+                            showing: stock_rules.draw,
+                        },
+                    ),
+                ];
+                slots.extend((0..klondike::FOUNDATIONS).map(|i| {
+                    PileSlot::new(PileId::Foundation(i), 3.0 + i as f32, 0.0, Fan::Stacked)
+                }));
+                slots.extend(
+                    (0..klondike::TABLEAU)
+                        .map(|i| PileSlot::new(PileId::Tableau(i), i as f32, 1.0, Fan::Down)),
+                );
+                Layout { slots }
+            }
+            Variant::Spider { .. } => {
+                let mut slots = vec![PileSlot::new(PileId::Stock, 9.0, 0.0, Fan::Stacked)];
+                slots
+                    .extend((0..spider::FOUNDATIONS).map(|i| {
+                        PileSlot::new(PileId::Foundation(i), i as f32, 0.0, Fan::Stacked)
+                    }));
+                slots.extend(
+                    (0..spider::TABLEAU)
+                        .map(|i| PileSlot::new(PileId::Tableau(i), i as f32, 1.0, Fan::Down)),
+                );
+                Layout { slots }
+            }
+            Variant::FreeCell => {
+                let mut slots: Vec<PileSlot> = (0..freecell::FREE_CELLS)
+                    .map(|i| PileSlot::new(PileId::FreeCell(i), i as f32, 0.0, Fan::Stacked))
+                    .collect();
+                slots.extend((0..freecell::FOUNDATIONS).map(|i| {
+                    PileSlot::new(PileId::Foundation(i), 4.0 + i as f32, 0.0, Fan::Stacked)
+                }));
+                slots.extend(
+                    (0..freecell::TABLEAU)
+                        .map(|i| PileSlot::new(PileId::Tableau(i), i as f32, 1.0, Fan::Down)),
+                );
+                Layout { slots }
+            }
+            Variant::Overlap(rules) => {
+                let spots = rules.spots();
+                // stock and waste go underneath, with Pyramid's foundation off to the right
+                let below = spots.iter().map(|s| s.row).fold(0.0, f32::max) + 1.25;
+                let right = spots.iter().map(|s| s.column).fold(0.0, f32::max);
+                let mut slots = vec![
+                    PileSlot::new(PileId::Stock, 0.0, below, Fan::Stacked),
+                    PileSlot::new(PileId::Waste, 1.0, below, Fan::Stacked),
+                ];
+                if rules == overlap::Rules::Pyramid {
+                    slots.push(PileSlot::new(
+                        PileId::Foundation(0),
+                        right,
+                        below,
+                        Fan::Stacked,
+                    ));
+                }
+                slots.extend(spots.iter().enumerate().map(|(i, s)| {
+                    PileSlot::new(PileId::Spot(i), s.column, s.row, Fan::Stacked).on_layer(s.layer)
+                }));
+                Layout { slots }
+            }
+            Variant::Custom(ref definition) => definition.layout(),
+        }
+    }
+}
+
+/// Where a game's piles go, in card sized steps from the top left with the first row at 0.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Layout {
+    pub slots: Vec<PileSlot>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PileSlot {
+    pub pile: PileId,
+    pub column: f32,
+    pub row: f32,
+    pub fan: Fan,
+    /// Cards in slots on a higher layer lie on top of ones on a lower layer where they overlap.
+    pub layer: usize,
+}
+
+impl PileSlot {
+    pub fn new(pile: PileId, column: f32, row: f32, fan: Fan) -> PileSlot {
+        PileSlot {
+            pile,
+            column,
+            row,
+            fan,
+            layer: 0,
+        }
+    }
+
+    pub fn on_layer(self, layer: usize) -> PileSlot {
+        PileSlot { layer, ..self }
+    }
+}
+
+/// How the cards of a pile spread out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Fan {
+    /// Squared up, only the top card shows.
+    #[default]
+    Stacked,
+    /// Down the table, every card shows.
+    Down,
+    /// The top `showing` cards spread out to the right.
+    Right { showing: usize },
+}
+
+/// A game in progress, whichever variant it is.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum VariantState {
+    Klondike(klondike::GameState),
+    Spider(spider::GameState),
+    FreeCell(freecell::GameState),
+    Overlap(overlap::GameState),
+    Custom(custom::GameState),
+}
+
+impl VariantState {
+    pub fn variant(&self) -> Variant {
+        match self {
+            VariantState::Klondike(g) => Variant::Klondike(g.stock_rules),
+            VariantState::Spider(g) => Variant::Spider { suits: g.suits },
+            VariantState::FreeCell(_) => Variant::FreeCell,
+            VariantState::Overlap(g) => Variant::Overlap(g.rules),
+            VariantState::Custom(g) => Variant::Custom(g.definition.clone()),
+        }
+    }
+
+    fn rules(&self) -> &dyn Rules {
+        match self {
+            VariantState::Klondike(g) => g,
+            VariantState::Spider(g) => g,
+            VariantState::FreeCell(g) => g,
+            VariantState::Overlap(g) => g,
+            VariantState::Custom(g) => g,
+        }
+    }
+
+    fn rules_mut(&mut self) -> &mut dyn Rules {
+        match self {
+            VariantState::Klondike(g) => g,
+            VariantState::Spider(g) => g,
+            VariantState::FreeCell(g) => g,
+            VariantState::Overlap(g) => g,
+            VariantState::Custom(g) => g,
+        }
+    }
+
+    pub fn piles(&self) -> Vec<PileId> {
+        self.rules().piles()
+    }
+
+    pub fn pile(&self, id: PileId) -> Option<&Vec<PileCard>> {
+        self.rules().pile(id)
+    }
+
+    /// Every card on the table.
+    pub fn cards(&self) -> Vec<Card> {
+        self.piles()
+            .into_iter()
+            .filter_map(|p| self.pile(p))
+            .flatten()
+            .map(|c| c.card)
+            .collect()
+    }
+
+    /// Which pile a card is in and how far up it sits, by `Card::id`.
+    pub fn find(&self, card_id: usize) -> Option<(PileId, usize)> {
+        self.piles().into_iter().find_map(|id| {
+            self.pile(id)?
+                .iter()
+                .position(|c| c.card.id() == card_id)
+                .map(|i| (id, i))
+        })
+    }
+
+    /// What clicking the stock does, if anything.
+    pub fn stock_move(&self) -> Option<Move> {
+        [Move::Draw, Move::Recycle]
+            .into_iter()
+            .find(|mv| self.check(*mv).is_ok())
+    }
+
+    pub fn check(&self, mv: Move) -> Result<(), IllegalMove> {
+        self.rules().check(mv)
+    }
+
+    pub fn can_pick_up(&self, from: PileId, count: usize) -> bool {
+        self.rules().can_pick_up(from, count)
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.rules().legal_moves()
+    }
+
+    pub fn apply(&mut self, mv: Move) -> Result<Applied, IllegalMove> {
+        self.rules_mut().apply(mv)
+    }
+
+    pub fn undo(&mut self, applied: &Applied) {
+        self.rules_mut().undo(applied)
+    }
+
+    pub fn is_won(&self) -> bool {
+        self.rules().is_won()
+    }
+}
@@ -0,0 +1,587 @@
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+    utils::HashMap,
+};
+use futures_lite::future;
+
+use super::pause::Paused;
+use crate::components::cards::{CardTarget, CardVisual, InPile, PileCards, CARD_SIZE};
+use crate::rules::{
+    autoplay,
+    history::History,
+    shuffle::DealSeed,
+    solver::{self, Verdict},
+    variant::{Fan, Variant, VariantState},
+    Applied, Move, PileCard, PileId,
+};
+use crate::{CardSlotPositions, MoveState, MoveThisCard};
+
+pub struct BoardPlugin;
+
+impl Plugin for BoardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayMove>()
+            .add_event::<MovePlayed>()
+            .add_event::<Undo>()
+            .add_event::<Redo>()
+            .add_event::<MoveUndone>()
+            .add_event::<AutoComplete>()
+            .add_event::<GameWon>()
+            .add_event::<NewGame>()
+            .add_event::<GameDealt>()
+            .init_resource::<GameClock>()
+            .init_resource::<WinnableOnly>()
+            .add_systems(Update, tick_clock)
+            .add_systems(
+                Update,
+                (
+                    play_moves,
+                    undo_redo,
+                    auto_complete,
+                    new_game,
+                    finish_dealing,
+                    detect_win,
+                    sync_cards_to_game,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Ask for a move to be played. Anything that wants to change the game sends one of these rather
+/// than poking at `Game` itself.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlayMove(pub Move);
+
+/// Sent for every move the rules accepted, redone ones included.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct MovePlayed(pub Applied);
+
+/// Take back the last move.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Undo;
+
+/// Play the last undone move again.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Redo;
+
+/// Sent for every move taken back by an `Undo`.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct MoveUndone(pub Applied);
+
+/// Put every remaining card away, only does anything once `autoplay::can_auto_complete` agrees.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct AutoComplete;
+
+/// Sent once when a move puts the last card on the foundations.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct GameWon;
+
+/// Throw the current game away and deal a random one with the same stock rules.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct NewGame;
+
+/// Sent once a `NewGame` has been dealt, which can be a while after it was asked for when only
+/// winnable deals will do.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct GameDealt;
+
+/// The game everything on screen is drawn from. Change `state` and the cards follow.
+#[derive(Resource)]
+pub struct Game {
+    pub state: VariantState,
+    pub deal: DealSeed,
+    pub history: History,
+}
+
+impl Game {
+    pub fn new(deal: DealSeed, variant: &Variant) -> Game {
+        Game {
+            state: variant.deal(deal),
+            deal,
+            history: History::default(),
+        }
+    }
+}
+
+/// Only deal random games the solver has found a way to win.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct WinnableOnly(pub bool);
+
+/// Positions the solver gets per deal when looking for a winnable one. Most winnable deals are
+/// found in a few hundred, the ones it can't settle this quickly get passed over.
+const WINNABLE_BUDGET: usize = 20_000;
+const WINNABLE_TRIES: usize = 50;
+
+/// Whether a new deal of `variant` has to go past the solver first, which only plays Klondike.
+pub fn needs_winnable_search(variant: &Variant, winnable_only: bool) -> bool {
+    winnable_only && matches!(variant, Variant::Klondike(_))
+}
+
+/// A random deal, FreeCell sticks to the numbered ones.
+pub fn random_deal(variant: &Variant) -> DealSeed {
+    match variant {
+        Variant::FreeCell => DealSeed::random_classic(),
+        _ => DealSeed::random(),
+    }
+}
+
+/// A random deal the solver has found a way to win. Going through the tries can take seconds, so
+/// this is for `Dealing` to run off the main thread.
+fn winnable_deal(variant: &Variant) -> DealSeed {
+    let Variant::Klondike(stock_rules) = *variant else {
+        return random_deal(variant);
+    };
+    for _ in 0..WINNABLE_TRIES {
+        let deal = DealSeed::random();
+        let report = solver::solve_deal(deal, stock_rules, WINNABLE_BUDGET);
+        if matches!(report.verdict, Verdict::Solvable(_)) {
+            return deal;
+        }
+    }
+    warn!("Couldn't find a winnable deal, this one might not be");
+    DealSeed::random()
+}
+
+/// A winnable deal being looked for on the async compute pool. The game on the table stays until
+/// it's found, but moves on it are turned down.
+#[derive(Resource)]
+pub struct Dealing {
+    variant: Variant,
+    search: Task<DealSeed>,
+}
+
+impl Dealing {
+    pub fn start(variant: Variant) -> Dealing {
+        let searching = variant.clone();
+        let search = AsyncComputeTaskPool::get().spawn(async move { winnable_deal(&searching) });
+        Dealing { variant, search }
+    }
+}
+
+/// Seconds spent on the current game. Kept out of `Game` so ticking it doesn't resync the board
+/// every frame.
+#[derive(Resource, Default)]
+pub struct GameClock {
+    pub elapsed: f32,
+}
+
+/// Stops once the game is won, so the summary shows how long it took, and while it's paused.
+fn tick_clock(
+    time: Res<Time>,
+    game: Option<Res<Game>>,
+    paused: Res<Paused>,
+    mut clock: ResMut<GameClock>,
+) {
+    if paused.open || game.is_some_and(|g| g.state.is_won()) {
+        return;
+    }
+    clock.elapsed += time.delta_seconds();
+}
+
+/// How far a card in a tableau column sits below a face up card under it, enough to read the
+/// corner.
+pub const TABLEAU_FAN: f32 = 18.0;
+/// How far it sits below a face down one, there's nothing to read so only a sliver shows.
+const FACE_DOWN_FAN: f32 = 6.0;
+/// A long column squeezes up to stay on screen, but never past this much of its usual spacing.
+const MOST_SQUEEZED: f32 = 0.3;
+/// How far apart the cards of a draw-three spread out on the waste.
+pub const WASTE_FAN: f32 = 14.0;
+/// Moving cards are raised above this so they pass over the piles they fly across.
+pub const TOP_OF_THE_STACK: f32 = 500.0;
+const MOVE_TIME: u128 = 300;
+/// Gap between cards starting to move when several move at once, so a deal comes off the stock
+/// one card at a time.
+const MOVE_STAGGER: u128 = 30;
+/// Gap between cards flying home during an auto-complete.
+const AUTO_COMPLETE_STAGGER: u128 = 90;
+
+pub fn pile_position(slots: &CardSlotPositions, pile: PileId) -> Vec2 {
+    slots
+        .get(pile)
+        .expect("Board slots haven't been generated for this pile.")
+        .position
+}
+
+pub fn card_position(
+    slots: &CardSlotPositions,
+    game: &VariantState,
+    pile: PileId,
+    index: usize,
+) -> Vec2 {
+    let slot = slots
+        .get(pile)
+        .expect("Board slots haven't been generated for this pile.");
+    let base = slot.position;
+    match slot.fan {
+        Fan::Stacked => base,
+        Fan::Down => {
+            let cards = game.pile(pile).map_or(&[][..], |p| p.as_slice());
+            // each card sits below the last by however much of that one needs to show
+            let gap = |c: &PileCard| {
+                if c.face_up {
+                    TABLEAU_FAN
+                } else {
+                    FACE_DOWN_FAN
+                }
+            };
+            let full: f32 = cards.iter().rev().skip(1).map(gap).sum();
+            let room = base.y - slots.floor;
+            let squeeze = if full > room {
+                (room / full).max(MOST_SQUEEZED)
+            } else {
+                1.0
+            };
+            let down: f32 = cards.iter().take(index).map(gap).sum();
+            base - Vec2::new(0.0, down * squeeze)
+        }
+        // the last few stay spread out so you can see what's under the top card
+        Fan::Right { showing } => {
+            let len = game.pile(pile).map_or(0, |p| p.len());
+            let spread_from = len.saturating_sub(showing);
+            let fanned = index.saturating_sub(spread_from);
+            base + Vec2::new(WASTE_FAN * fanned as f32, 0.0)
+        }
+    }
+}
+
+/// How far up the pile of cards on the table a card goes, its place in its own pile on top of
+/// its slot's layer so cards that lie across others in the rules get drawn over them too.
+pub fn card_height(slots: &CardSlotPositions, pile: PileId, index: usize) -> f32 {
+    let layer = slots.get(pile).map_or(0, |slot| slot.layer);
+    (layer + index) as f32
+}
+
+/// The pile a card dropped with its centre at `card_center` lands on, whichever slot or top card
+/// it covers the most of. Never the stock, and only spots with a card nothing lies across, the
+/// others are behind what it's meant to land on.
+pub fn drop_target(
+    game: &VariantState,
+    slots: &CardSlotPositions,
+    card_center: Vec2,
+) -> Option<PileId> {
+    let dropped = Rect::from_center_size(card_center, CARD_SIZE);
+    game.piles()
+        .into_iter()
+        .filter(|&p| match p {
+            PileId::Stock => false,
+            PileId::Spot(_) => game.can_pick_up(p, 1),
+            _ => true,
+        })
+        .map(|p| {
+            let top = game.pile(p).map_or(0, |c| c.len().saturating_sub(1));
+            let target = Rect::from_center_size(card_position(slots, game, p, top), CARD_SIZE);
+            let overlap = dropped.intersect(target).size();
+            (p, overlap.x * overlap.y)
+        })
+        .filter(|(_, area)| *area > 0.0)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(p, _)| p)
+}
+
+pub fn is_face_up(tx: &Transform) -> bool {
+    tx.back().dot(Vec3::Z) > 0.0
+}
+
+fn play_moves(
+    mut game: ResMut<Game>,
+    dealing: Option<Res<Dealing>>,
+    mut requests: EventReader<PlayMove>,
+    mut played: EventWriter<MovePlayed>,
+) {
+    // this game's on its way out, put back anything that got dragged off
+    if dealing.is_some() {
+        if requests.iter().count() > 0 {
+            game.set_changed();
+        }
+        return;
+    }
+    for PlayMove(mv) in requests.iter() {
+        match game.state.apply(*mv) {
+            Ok(applied) => {
+                game.history.record(applied);
+                played.send(MovePlayed(applied));
+            }
+            Err(e) => {
+                debug!("{:?} isn't allowed: {:?}", mv, e);
+                // nothing changed, but anything that got dragged off needs to go back home
+                game.set_changed();
+            }
+        }
+    }
+}
+
+fn undo_redo(
+    mut game: ResMut<Game>,
+    mut undos: EventReader<Undo>,
+    mut redos: EventReader<Redo>,
+    mut played: EventWriter<MovePlayed>,
+    mut undone: EventWriter<MoveUndone>,
+) {
+    // only borrow mutably when there's something to do, or the board resyncs every frame
+    for _ in undos.iter() {
+        let game = game.as_mut();
+        if let Some(applied) = game.history.undo(&mut game.state) {
+            undone.send(MoveUndone(applied));
+        }
+    }
+    for _ in redos.iter() {
+        let game = game.as_mut();
+        match game.history.redo(&mut game.state) {
+            Ok(Some(applied)) => played.send(MovePlayed(applied)),
+            Ok(None) => {}
+            Err(e) => warn!(
+                "Couldn't redo, dropping the rest of the undone moves: {:?}",
+                e
+            ),
+        }
+    }
+}
+
+fn new_game(
+    mut commands: Commands,
+    mut requests: EventReader<NewGame>,
+    mut game: ResMut<Game>,
+    mut clock: ResMut<GameClock>,
+    winnable_only: Res<WinnableOnly>,
+    mut dealt: EventWriter<GameDealt>,
+) {
+    if requests.iter().count() == 0 {
+        return;
+    }
+    let variant = game.state.variant();
+    // asking again while one's being looked for starts the search over
+    if needs_winnable_search(&variant, winnable_only.0) {
+        commands.insert_resource(Dealing::start(variant));
+        return;
+    }
+    commands.remove_resource::<Dealing>();
+    *game = Game::new(random_deal(&variant), &variant);
+    *clock = GameClock::default();
+    dealt.send(GameDealt);
+    info!("Playing deal {}", game.deal);
+}
+
+/// Puts the winnable deal on the table once `Dealing` has found it.
+fn finish_dealing(
+    mut commands: Commands,
+    dealing: Option<ResMut<Dealing>>,
+    mut game: ResMut<Game>,
+    mut clock: ResMut<GameClock>,
+    mut dealt: EventWriter<GameDealt>,
+) {
+    let Some(mut dealing) = dealing else {
+        return;
+    };
+    let Some(deal) = future::block_on(future::poll_once(&mut dealing.search)) else {
+        return;
+    };
+    commands.remove_resource::<Dealing>();
+    *game = Game::new(deal, &dealing.variant);
+    *clock = GameClock::default();
+    dealt.send(GameDealt);
+    info!("Playing deal {}", game.deal);
+}
+
+fn detect_win(game: Res<Game>, mut played: EventReader<MovePlayed>, mut won: EventWriter<GameWon>) {
+    if played.iter().count() > 0 && game.state.is_won() {
+        won.send(GameWon);
+    }
+}
+
+/// Sends a card over to its `CardTarget`, starting at `start` ms.
+fn send_card(
+    commands: &mut Commands,
+    visual: Entity,
+    visual_tx: &Transform,
+    start: u128,
+    face_up: bool,
+) {
+    commands.entity(visual).insert(MoveThisCard {
+        start_position: visual_tx.translation.truncate(),
+        start_rotation: visual_tx.rotation,
+        moving: MoveState::StartMove,
+        time_at_start_of_move: start,
+        time_to_finish_move: start + MOVE_TIME,
+        rotation_freqs: (0, 0, 0),
+        face_up,
+    });
+}
+
+/// Every card with where it's headed, and the piles they're filed in.
+#[derive(SystemParam)]
+struct TableCards<'w, 's> {
+    visuals: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Transform,
+            &'static CardVisual,
+            Option<&'static MoveThisCard>,
+        ),
+    >,
+    targets: Query<'w, 's, &'static mut CardTarget>,
+    pile_cards: Query<'w, 's, &'static mut PileCards>,
+}
+
+/// Plays every move of the auto-complete at once, then sets the cards off one after another
+/// instead of leaving it to the board sync.
+fn auto_complete(
+    mut commands: Commands,
+    mut requests: EventReader<AutoComplete>,
+    mut game: ResMut<Game>,
+    mut played: EventWriter<MovePlayed>,
+    slots: Res<CardSlotPositions>,
+    time: Res<Time>,
+    table: TableCards,
+) {
+    if requests.iter().count() == 0 || !autoplay::can_auto_complete(&game.state) {
+        return;
+    }
+    let TableCards {
+        visuals,
+        mut targets,
+        mut pile_cards,
+    } = table;
+    let visual_for_card: HashMap<usize, (Entity, &Transform)> = visuals
+        .iter()
+        .map(|(e, tx, c, _)| (c.id(), (e, tx)))
+        .collect();
+    let now = time.elapsed().as_millis();
+    // the sync would send them all at once, these moves are ours to time
+    let game = game.bypass_change_detection();
+    let visuals_by_id: HashMap<usize, Entity> = visual_for_card
+        .iter()
+        .map(|(&id, &(e, _))| (id, e))
+        .collect();
+    for (k, mv) in autoplay::auto_complete(&game.state).into_iter().enumerate() {
+        let applied = game
+            .state
+            .apply(mv)
+            .expect("auto_complete only hands out legal moves");
+        game.history.record(applied);
+        played.send(MovePlayed(applied));
+        let Move::Transfer { to, .. } = mv else {
+            continue;
+        };
+        let dest = game.state.pile(to).expect("just moved a card here");
+        let index = dest.len() - 1;
+        let card = dest[index].card;
+        let (visual, visual_tx) = visual_for_card[&card.id()];
+        targets
+            .get_mut(visual)
+            .expect("Card without a CardTarget during auto-complete.")
+            .0 = card_position(&slots, &game.state, to, index);
+        let start = now + k as u128 * AUTO_COMPLETE_STAGGER;
+        send_card(&mut commands, visual, visual_tx, start, true);
+    }
+    file_cards(
+        &mut commands,
+        &game.state,
+        &slots,
+        &visuals_by_id,
+        &mut pile_cards,
+    );
+}
+
+/// Brings every card's `InPile` and every pile's `PileCards` into line with the game.
+fn file_cards(
+    commands: &mut Commands,
+    game: &VariantState,
+    slots: &CardSlotPositions,
+    visual_for_card: &HashMap<usize, Entity>,
+    pile_cards: &mut Query<&mut PileCards>,
+) {
+    for pile in game.piles() {
+        let Some(slot) = slots.get(pile) else {
+            continue;
+        };
+        let Ok(mut filed) = pile_cards.get_mut(slot.entity) else {
+            continue;
+        };
+        filed.0.clear();
+        let cards = game.pile(pile).expect("piles() only lists real piles");
+        for (index, pc) in cards.iter().enumerate() {
+            let Some(&visual) = visual_for_card.get(&pc.card.id()) else {
+                continue;
+            };
+            commands.entity(visual).insert(InPile {
+                pile: slot.entity,
+                index,
+            });
+            filed.0.push(visual);
+        }
+    }
+}
+
+/// Files every card in its pile, points its `CardTarget` where the game says it belongs, and
+/// sends it over with `MoveThisCard` when it isn't already there. That
+/// happens when the piles move to fit the window too, all together rather than one after
+/// another like a deal.
+fn sync_cards_to_game(
+    mut commands: Commands,
+    game: Res<Game>,
+    slots: Res<CardSlotPositions>,
+    time: Res<Time>,
+    table: TableCards,
+) {
+    // once it's won the cascade has the cards, they stay with it through a resize
+    let relaid_out = slots.is_changed() && !game.state.is_won();
+    if !game.is_changed() && !relaid_out {
+        return;
+    }
+    let stagger = if game.is_changed() { MOVE_STAGGER } else { 0 };
+    let TableCards {
+        visuals,
+        mut targets,
+        mut pile_cards,
+    } = table;
+    let visual_for_card: HashMap<usize, (Entity, &Transform, Option<&MoveThisCard>)> = visuals
+        .iter()
+        .map(|(e, tx, c, m)| (c.id(), (e, tx, m)))
+        .collect();
+    let visuals_by_id: HashMap<usize, Entity> = visual_for_card
+        .iter()
+        .map(|(&id, &(e, _, _))| (id, e))
+        .collect();
+    file_cards(
+        &mut commands,
+        &game.state,
+        &slots,
+        &visuals_by_id,
+        &mut pile_cards,
+    );
+    let now = time.elapsed().as_millis();
+    let mut started = 0;
+    for pile in game.state.piles() {
+        let cards = game
+            .state
+            .pile(pile)
+            .expect("piles() only lists real piles");
+        for (i, pc) in cards.iter().enumerate() {
+            let Some(&(visual, visual_tx, moving)) = visual_for_card.get(&pc.card.id()) else {
+                continue;
+            };
+            let Ok(mut card_target) = targets.get_mut(visual) else {
+                continue;
+            };
+            let target = card_position(&slots, &game.state, pile, i);
+            if moving.is_some() && card_target.0 == target {
+                continue;
+            }
+            card_target.0 = target;
+            let at_rest = visual_tx.translation.truncate().distance(target) < 0.5
+                && is_face_up(visual_tx) == pc.face_up;
+            if at_rest {
+                continue;
+            }
+            let start = now + started * stagger;
+            send_card(&mut commands, visual, visual_tx, start, pc.face_up);
+            started += 1;
+        }
+    }
+}
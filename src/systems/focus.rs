@@ -0,0 +1,337 @@
+//! Playing without the mouse. The arrow keys move a focus outline between piles and up and down
+//! the face up cards of a column, Enter picks up what's focused and puts it down again, Tab jumps
+//! between the places it could legally go and Space turns over the stock. A controller drives the
+//! same focus through `FocusInput`, see `systems::gamepad`.
+
+use bevy::prelude::*;
+
+use super::board::{card_position, pile_position, Game, MovePlayed, PlayMove};
+use super::pause::Paused;
+use crate::components::cards::CARD_SIZE;
+use crate::rules::{variant::Fan, Move, PileId};
+use crate::CardSlotPositions;
+
+pub struct FocusPlugin;
+
+impl Plugin for FocusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Focus>()
+            .add_event::<FocusInput>()
+            .add_systems(
+                Update,
+                (
+                    keep_focus_on_the_table,
+                    keyboard_focus,
+                    move_focus,
+                    draw_focus,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// One step of playing without the mouse, from a key or a controller button.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FocusInput {
+    Left,
+    Right,
+    Up,
+    Down,
+    /// Pick up what's focused, or put down what's held.
+    Select,
+    /// On to the next place what's held can go, picking up what's focused first. `back` goes
+    /// round the other way.
+    NextDestination {
+        back: bool,
+    },
+    /// Put back what's held.
+    Cancel,
+    /// Turn over the stock.
+    Draw,
+}
+
+/// Where the keyboard or controller is pointing and what it's holding. Nothing gets drawn until
+/// one of them is used, and a mouse click hides it again.
+#[derive(Resource, Default, Debug)]
+pub struct Focus {
+    pile: Option<PileId>,
+    /// The card in `pile` the focus is on, everything above it gets picked up with it.
+    index: usize,
+    held: Option<Held>,
+    shown: bool,
+}
+
+/// Cards picked up from the keyboard, they stay where they are until they're put down.
+#[derive(Clone, Copy, Debug)]
+struct Held {
+    from: PileId,
+    count: usize,
+}
+
+impl Focus {
+    pub fn is_holding(&self) -> bool {
+        self.held.is_some()
+    }
+}
+
+const FOCUS_COLOR: Color = Color::CYAN;
+const HELD_COLOR: Color = Color::ORANGE;
+/// The outline sits this far outside the cards so it doesn't hide their edges.
+const OUTLINE_MARGIN: f32 = 4.0;
+
+/// Moves the focus back onto a card that's there after the game changes under it, and lets go of
+/// anything held since the cards it was holding may have gone somewhere else. Auto-complete
+/// doesn't mark `Game` changed, so its played moves count as well.
+fn keep_focus_on_the_table(
+    game: Res<Game>,
+    mouse_clicks: Res<Input<MouseButton>>,
+    mut played: EventReader<MovePlayed>,
+    mut focus: ResMut<Focus>,
+) {
+    if mouse_clicks.get_just_pressed().next().is_some() {
+        focus.shown = false;
+        focus.held = None;
+    }
+    if played.iter().count() == 0 && !game.is_changed() {
+        return;
+    }
+    focus.held = None;
+    let piles = game.state.piles();
+    let pile = match focus.pile {
+        Some(pile) if piles.contains(&pile) => pile,
+        // the first column, or whatever comes first in games without one
+        _ => match piles.iter().find(|p| matches!(p, PileId::Tableau(_))) {
+            Some(&pile) => pile,
+            None => match piles.first() {
+                Some(&pile) => pile,
+                None => return,
+            },
+        },
+    };
+    let len = game.state.pile(pile).map_or(0, |p| p.len());
+    focus.pile = Some(pile);
+    focus.index = focus.index.min(len.saturating_sub(1));
+}
+
+/// Turns key presses into focus inputs.
+fn keyboard_focus(keys: Res<Input<KeyCode>>, mut inputs: EventWriter<FocusInput>) {
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    for key in keys.get_just_pressed() {
+        let input = match key {
+            KeyCode::Left => FocusInput::Left,
+            KeyCode::Right => FocusInput::Right,
+            KeyCode::Up => FocusInput::Up,
+            KeyCode::Down => FocusInput::Down,
+            KeyCode::Return => FocusInput::Select,
+            KeyCode::Tab => FocusInput::NextDestination { back: shift },
+            KeyCode::Back => FocusInput::Cancel,
+            KeyCode::Space => FocusInput::Draw,
+            _ => continue,
+        };
+        inputs.send(input);
+    }
+}
+
+pub fn move_focus(
+    mut inputs: EventReader<FocusInput>,
+    game: Res<Game>,
+    slots: Res<CardSlotPositions>,
+    paused: Res<Paused>,
+    mut focus: ResMut<Focus>,
+    mut moves: EventWriter<PlayMove>,
+) {
+    // the pause menu has the inputs while it's open
+    if paused.open || game.state.is_won() {
+        inputs.clear();
+        return;
+    }
+    for &input in inputs.iter() {
+        let Some(pile) = focus.pile else {
+            return;
+        };
+        // the first step in any direction only shows where the focus is
+        let hidden = !focus.shown;
+        focus.shown = true;
+        let steps = [
+            FocusInput::Left,
+            FocusInput::Right,
+            FocusInput::Up,
+            FocusInput::Down,
+        ];
+        if hidden && steps.contains(&input) {
+            continue;
+        }
+        let focus = focus.as_mut();
+        let len = |pile: PileId| game.state.pile(pile).map_or(0, |p| p.len());
+        match input {
+            FocusInput::Left | FocusInput::Right => {
+                let direction = if input == FocusInput::Left {
+                    -Vec2::X
+                } else {
+                    Vec2::X
+                };
+                if let Some(next) = neighbour(&game, &slots, pile, direction) {
+                    go_to(focus, &game, next);
+                }
+            }
+            FocusInput::Up => {
+                if fanned_down(&slots, pile)
+                    && focus.index > 0
+                    && is_face_up(&game, pile, focus.index - 1)
+                {
+                    focus.index -= 1;
+                } else if let Some(next) = neighbour(&game, &slots, pile, Vec2::Y) {
+                    go_to(focus, &game, next);
+                }
+            }
+            FocusInput::Down => {
+                if fanned_down(&slots, pile) && focus.index + 1 < len(pile) {
+                    focus.index += 1;
+                } else if let Some(next) = neighbour(&game, &slots, pile, -Vec2::Y) {
+                    go_to(focus, &game, next);
+                }
+            }
+            FocusInput::NextDestination { back } => {
+                // picks up what's focused first if nothing's held yet
+                let Some(held) = focus.held.or_else(|| pick_up(&game, pile, focus.index)) else {
+                    continue;
+                };
+                let mut destinations = game.state.piles();
+                destinations.retain(|&to| {
+                    game.state
+                        .check(Move::Transfer {
+                            from: held.from,
+                            to,
+                            count: held.count,
+                        })
+                        .is_ok()
+                });
+                if back {
+                    destinations.reverse();
+                }
+                // the next one along from the focus, in the order the game lists its piles
+                let at = destinations.iter().position(|&p| p == pile);
+                let next = match at {
+                    Some(i) => destinations.get(i + 1).or(destinations.first()),
+                    None => destinations.first(),
+                };
+                if let Some(&next) = next {
+                    focus.held = Some(held);
+                    go_to(focus, &game, next);
+                }
+            }
+            FocusInput::Cancel => focus.held = None,
+            FocusInput::Draw => {
+                // a stock with nothing left to give just ignores it
+                if let (Some(_), Some(mv)) = (slots.get(PileId::Stock), game.state.stock_move()) {
+                    moves.send(PlayMove(mv));
+                }
+            }
+            FocusInput::Select => match focus.held.take() {
+                // putting them back where they came from
+                Some(held) if held.from == pile => {}
+                Some(held) => moves.send(PlayMove(Move::Transfer {
+                    from: held.from,
+                    to: pile,
+                    count: held.count,
+                })),
+                None if pile == PileId::Stock => {
+                    if let Some(mv) = game.state.stock_move() {
+                        moves.send(PlayMove(mv));
+                    }
+                }
+                None => focus.held = pick_up(&game, pile, focus.index),
+            },
+        }
+    }
+}
+
+fn go_to(focus: &mut Focus, game: &Game, pile: PileId) {
+    focus.pile = Some(pile);
+    focus.index = game
+        .state
+        .pile(pile)
+        .map_or(0, |p| p.len().saturating_sub(1));
+}
+
+/// The cards from `index` up, if they can be lifted off together.
+fn pick_up(game: &Game, pile: PileId, index: usize) -> Option<Held> {
+    let count = game.state.pile(pile)?.len().checked_sub(index)?;
+    (pile != PileId::Stock && count > 0 && game.state.can_pick_up(pile, count))
+        .then_some(Held { from: pile, count })
+}
+
+fn fanned_down(slots: &CardSlotPositions, pile: PileId) -> bool {
+    slots.get(pile).is_some_and(|slot| slot.fan == Fan::Down)
+}
+
+fn is_face_up(game: &Game, pile: PileId, index: usize) -> bool {
+    game.state
+        .pile(pile)
+        .and_then(|p| p.get(index))
+        .is_some_and(|c| c.face_up)
+}
+
+/// The closest pile in `direction` from `from` on the table, straight along it counting for more
+/// than off to the side. Spots that have been cleared are passed over.
+fn neighbour(
+    game: &Game,
+    slots: &CardSlotPositions,
+    from: PileId,
+    direction: Vec2,
+) -> Option<PileId> {
+    let here = slots.get(from)?.position;
+    slots
+        .slots
+        .iter()
+        .filter(|s| s.pile != from)
+        .filter(|s| {
+            !matches!(s.pile, PileId::Spot(_))
+                || game.state.pile(s.pile).is_some_and(|p| !p.is_empty())
+        })
+        .filter_map(|s| {
+            let d = s.position - here;
+            let along = d.dot(direction);
+            let aside = d.perp_dot(direction).abs();
+            (along > 1.0).then_some((s.pile, along + 2.0 * aside))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(pile, _)| pile)
+}
+
+/// Outlines the focused cards, from the focused one to the top of its pile, and anything held.
+fn draw_focus(
+    mut gizmos: Gizmos,
+    focus: Res<Focus>,
+    game: Res<Game>,
+    slots: Res<CardSlotPositions>,
+) {
+    let (true, Some(pile)) = (focus.shown, focus.pile) else {
+        return;
+    };
+    let run = |pile: PileId, from: usize| {
+        let len = game.state.pile(pile).map_or(0, |p| p.len());
+        if len == 0 {
+            return Rect::from_center_size(pile_position(&slots, pile), CARD_SIZE);
+        }
+        let card =
+            |i| Rect::from_center_size(card_position(&slots, &game.state, pile, i), CARD_SIZE);
+        card(from.min(len - 1)).union(card(len - 1))
+    };
+    let mut outline = |rect: Rect, color: Color| {
+        let rect = rect.inset(OUTLINE_MARGIN);
+        gizmos.rect_2d(rect.center(), 0.0, rect.size(), color);
+    };
+    if let Some(held) = focus.held {
+        let len = game.state.pile(held.from).map_or(0, |p| p.len());
+        outline(run(held.from, len.saturating_sub(held.count)), HELD_COLOR);
+        // where Enter would put them down
+        let top = game
+            .state
+            .pile(pile)
+            .map_or(0, |p| p.len().saturating_sub(1));
+        outline(run(pile, top), FOCUS_COLOR);
+    } else {
+        outline(run(pile, focus.index), FOCUS_COLOR);
+    }
+}
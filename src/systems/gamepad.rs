@@ -0,0 +1,150 @@
+//! Playing with a controller. The D-pad or the left stick moves the same focus the arrow keys do,
+//! repeating while it's held, A picks up and puts down, B puts back what's held or takes back the
+//! last move, the shoulder buttons step through the hints and Start pauses.
+
+use bevy::prelude::*;
+
+use super::board::Undo;
+use super::focus::{Focus, FocusInput};
+use super::hint::ShowHint;
+use super::pause::{Paused, TogglePause};
+
+pub struct GamepadPlugin;
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StickRepeat>()
+            .add_systems(Update, (gamepad_navigation, gamepad_pause, gamepad_buttons));
+    }
+}
+
+/// How far the stick has to be pushed before it counts, a worn stick rests a little off centre.
+const DEAD_ZONE: f32 = 0.5;
+/// Holding a direction moves once, waits this long, then keeps moving.
+const REPEAT_DELAY: f32 = 0.4;
+const REPEAT_GAP: f32 = 0.12;
+
+/// The direction being held on any controller and when it next moves the focus again.
+#[derive(Resource, Default, Debug)]
+struct StickRepeat {
+    held: Option<FocusInput>,
+    next_at: f32,
+}
+
+/// The way the D-pad or the left stick is pushed, the stick going by whichever axis is pushed
+/// furthest.
+fn direction(
+    gamepad: Gamepad,
+    buttons: &Input<GamepadButton>,
+    axes: &Axis<GamepadAxis>,
+) -> Option<FocusInput> {
+    let dpad = [
+        (GamepadButtonType::DPadLeft, FocusInput::Left),
+        (GamepadButtonType::DPadRight, FocusInput::Right),
+        (GamepadButtonType::DPadUp, FocusInput::Up),
+        (GamepadButtonType::DPadDown, FocusInput::Down),
+    ];
+    if let Some(&(_, input)) = dpad
+        .iter()
+        .find(|(button, _)| buttons.pressed(GamepadButton::new(gamepad, *button)))
+    {
+        return Some(input);
+    }
+    let axis = |kind| axes.get(GamepadAxis::new(gamepad, kind)).unwrap_or(0.0);
+    let stick = Vec2::new(
+        axis(GamepadAxisType::LeftStickX),
+        axis(GamepadAxisType::LeftStickY),
+    );
+    if stick.abs().max_element() < DEAD_ZONE {
+        None
+    } else if stick.x.abs() > stick.y.abs() {
+        Some(if stick.x < 0.0 {
+            FocusInput::Left
+        } else {
+            FocusInput::Right
+        })
+    } else {
+        Some(if stick.y < 0.0 {
+            FocusInput::Down
+        } else {
+            FocusInput::Up
+        })
+    }
+}
+
+fn gamepad_navigation(
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    time: Res<Time>,
+    mut repeat: ResMut<StickRepeat>,
+    mut inputs: EventWriter<FocusInput>,
+) {
+    let now = time.elapsed_seconds();
+    let held = gamepads
+        .iter()
+        .find_map(|gamepad| direction(gamepad, &buttons, &axes));
+    if held != repeat.held {
+        repeat.held = held;
+        repeat.next_at = now + REPEAT_DELAY;
+        if let Some(input) = held {
+            inputs.send(input);
+        }
+    } else if let Some(input) = held.filter(|_| now >= repeat.next_at) {
+        repeat.next_at = now + REPEAT_GAP;
+        inputs.send(input);
+    }
+}
+
+fn gamepad_pause(
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    mut pause: EventWriter<TogglePause>,
+) {
+    for gamepad in gamepads.iter() {
+        if buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::Start)) {
+            pause.send(TogglePause);
+        }
+    }
+}
+
+fn gamepad_buttons(
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    focus: Res<Focus>,
+    paused: Res<Paused>,
+    mut inputs: EventWriter<FocusInput>,
+    mut undo: EventWriter<Undo>,
+    mut hint: EventWriter<ShowHint>,
+) {
+    for gamepad in gamepads.iter() {
+        let pressed = |kind| buttons.just_pressed(GamepadButton::new(gamepad, kind));
+        if pressed(GamepadButtonType::South) {
+            inputs.send(FocusInput::Select);
+        }
+        if pressed(GamepadButtonType::East) {
+            // with nothing held, or the menu open, there's nothing to put back
+            if focus.is_holding() || paused.open {
+                inputs.send(FocusInput::Cancel);
+            } else {
+                undo.send(Undo);
+            }
+        }
+        // the rest are for the table, the menu doesn't use them
+        if paused.open {
+            continue;
+        }
+        if pressed(GamepadButtonType::North) {
+            inputs.send(FocusInput::NextDestination { back: false });
+        }
+        if pressed(GamepadButtonType::West) {
+            inputs.send(FocusInput::Draw);
+        }
+        if pressed(GamepadButtonType::LeftTrigger) {
+            hint.send(ShowHint { back: true });
+        }
+        if pressed(GamepadButtonType::RightTrigger) {
+            hint.send(ShowHint { back: false });
+        }
+    }
+}
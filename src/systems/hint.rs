@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::board::{card_position, pile_position, Game, MovePlayed};
+use crate::components::cards::CARD_SIZE;
+use crate::rules::{autoplay, Move, PileId};
+use crate::CardSlotPositions;
+
+pub struct HintPlugin;
+
+impl Plugin for HintPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ShowHint>()
+            .init_resource::<Hint>()
+            .add_systems(Update, (show_hint, draw_hint, no_moves_message).chain());
+    }
+}
+
+/// Point out a move, or the next one along if a hint is already showing. `back` goes to the one
+/// before instead.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ShowHint {
+    pub back: bool,
+}
+
+/// The hints for the current position, worked out on the first request and thrown away as soon
+/// as anything changes. Auto-complete plays its moves without marking `Game` changed, so a played
+/// move throws them away too.
+#[derive(Resource, Default)]
+struct Hint {
+    moves: Vec<Move>,
+    shown: Option<usize>,
+    /// Keeps "no moves available" up until this many seconds in.
+    no_moves_until: f32,
+}
+
+const SOURCE_COLOR: Color = Color::YELLOW;
+const DESTINATION_COLOR: Color = Color::GREEN;
+const NO_MOVES_SECS: f32 = 2.0;
+
+fn show_hint(
+    mut requests: EventReader<ShowHint>,
+    game: Res<Game>,
+    time: Res<Time>,
+    mut played: EventReader<MovePlayed>,
+    mut hint: ResMut<Hint>,
+) {
+    if played.iter().count() > 0 || game.is_changed() {
+        hint.moves.clear();
+        hint.shown = None;
+    }
+    for request in requests.iter() {
+        if hint.shown.is_none() {
+            hint.moves = autoplay::hints(&game.state);
+        }
+        if hint.moves.is_empty() {
+            hint.no_moves_until = time.elapsed_seconds() + NO_MOVES_SECS;
+            continue;
+        }
+        let len = hint.moves.len();
+        let next = match (hint.shown, request.back) {
+            (None, false) => 0,
+            (None, true) => len - 1,
+            (Some(i), false) => (i + 1) % len,
+            (Some(i), true) => (i + len - 1) % len,
+        };
+        hint.shown = Some(next);
+    }
+}
+
+/// Outlines the cards the hint would move and where they'd go, drawn with gizmos the same way
+/// the debug rects are.
+fn draw_hint(mut gizmos: Gizmos, hint: Res<Hint>, game: Res<Game>, slots: Res<CardSlotPositions>) {
+    let Some(&mv) = hint.shown.and_then(|i| hint.moves.get(i)) else {
+        return;
+    };
+    let card_rect = |pile: PileId, index: usize| {
+        Rect::from_center_size(card_position(&slots, &game.state, pile, index), CARD_SIZE)
+    };
+    let pile_len = |pile: PileId| game.state.pile(pile).map_or(0, |p| p.len());
+    let (source, destination) = match mv {
+        // turning the stock over, nowhere in particular for it to go
+        Move::Draw | Move::Recycle => (
+            Rect::from_center_size(pile_position(&slots, PileId::Stock), CARD_SIZE),
+            None,
+        ),
+        Move::Transfer { from, to, count } => {
+            let len = pile_len(from);
+            // a hint from before the cards went elsewhere, draw nothing rather than run off the end
+            if count == 0 || count > len {
+                return;
+            }
+            let moving = card_rect(from, len - count).union(card_rect(from, len - 1));
+            let onto = card_rect(to, pile_len(to).saturating_sub(1));
+            (moving, Some(onto))
+        }
+    };
+    gizmos.rect_2d(source.center(), 0.0, source.size(), SOURCE_COLOR);
+    if let Some(destination) = destination {
+        gizmos.rect_2d(
+            destination.center(),
+            0.0,
+            destination.size(),
+            DESTINATION_COLOR,
+        );
+    }
+}
+
+fn no_moves_message(mut contexts: EguiContexts, time: Res<Time>, hint: Res<Hint>) {
+    if time.elapsed_seconds() >= hint.no_moves_until {
+        return;
+    }
+    egui::Area::new("no moves")
+        .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -16.0])
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label("No moves available");
+        });
+}
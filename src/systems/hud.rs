@@ -0,0 +1,46 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::board::{AutoComplete, Dealing, Game, GameClock};
+use super::score::{Score, ScoreSettings};
+use crate::rules::autoplay;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, hud_ui);
+    }
+}
+
+fn hud_ui(
+    mut contexts: EguiContexts,
+    game: Option<Res<Game>>,
+    clock: Res<GameClock>,
+    score: Res<Score>,
+    score_settings: Res<ScoreSettings>,
+    dealing: Option<Res<Dealing>>,
+    mut auto_complete: EventWriter<AutoComplete>,
+) {
+    let Some(game) = game else {
+        return;
+    };
+    egui::Area::new("hud")
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 8.0])
+        .show(contexts.ctx_mut(), |ui| {
+            let secs = clock.elapsed as u32;
+            ui.label(format!(
+                "Deal {}    {}:{:02}    {}",
+                game.deal,
+                secs / 60,
+                secs % 60,
+                score.text(&score_settings, clock.elapsed)
+            ));
+            if dealing.is_some() {
+                ui.label("Dealing…");
+            }
+            if autoplay::can_auto_complete(&game.state) && ui.button("Finish the game").clicked() {
+                auto_complete.send(AutoComplete);
+            }
+        });
+}
@@ -1,2 +1,12 @@
+pub mod board;
 pub mod cards;
+pub mod focus;
+pub mod gamepad;
+pub mod hint;
+pub mod hud;
+pub mod pause;
+pub mod save;
+pub mod score;
+pub mod table;
+pub mod win;
 
@@ -0,0 +1,96 @@
+//! The pause menu, opened with Start on a controller or P on the keyboard. The clock stops and the
+//! board ignores the mouse while it's open, and the focus inputs move through its buttons instead
+//! of round the table.
+
+use bevy::{app::AppExit, prelude::*};
+use bevy_egui::EguiContexts;
+
+use super::board::NewGame;
+use super::focus::{move_focus, FocusInput};
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Paused>()
+            .add_event::<TogglePause>()
+            // after the focus has turned down this frame's inputs, so the Select that closes the
+            // menu doesn't go on to pick up a card as well
+            .add_systems(Update, (toggle_pause, pause_menu).chain().after(move_focus));
+    }
+}
+
+/// Open the pause menu, or close it if it's open.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TogglePause;
+
+#[derive(Resource, Default, Debug)]
+pub struct Paused {
+    pub open: bool,
+    /// The button the focus inputs are on.
+    selected: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MenuItem {
+    Resume,
+    NewGame,
+    Quit,
+}
+
+const MENU: [(MenuItem, &str); 3] = [
+    (MenuItem::Resume, "Resume"),
+    (MenuItem::NewGame, "New game"),
+    (MenuItem::Quit, "Quit"),
+];
+
+fn toggle_pause(mut toggles: EventReader<TogglePause>, mut paused: ResMut<Paused>) {
+    for _ in toggles.iter() {
+        paused.open = !paused.open;
+        paused.selected = 0;
+    }
+}
+
+fn pause_menu(
+    mut contexts: EguiContexts,
+    mut inputs: EventReader<FocusInput>,
+    mut paused: ResMut<Paused>,
+    mut new_game: EventWriter<NewGame>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if !paused.open {
+        inputs.clear();
+        return;
+    }
+    let mut chosen = None;
+    for input in inputs.iter() {
+        match input {
+            FocusInput::Up => paused.selected = paused.selected.saturating_sub(1),
+            FocusInput::Down => paused.selected = (paused.selected + 1).min(MENU.len() - 1),
+            FocusInput::Select => chosen = Some(MENU[paused.selected].0),
+            FocusInput::Cancel => chosen = Some(MenuItem::Resume),
+            _ => {}
+        }
+    }
+    egui::Window::new("Paused")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .collapsible(false)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for (i, &(item, label)) in MENU.iter().enumerate() {
+                if ui.selectable_label(paused.selected == i, label).clicked() {
+                    chosen = Some(item);
+                }
+            }
+        });
+    match chosen {
+        Some(MenuItem::Resume) => paused.open = false,
+        Some(MenuItem::NewGame) => {
+            paused.open = false;
+            new_game.send(NewGame);
+        }
+        // the save gets written on the way out
+        Some(MenuItem::Quit) => exit.send(AppExit),
+        None => {}
+    }
+}
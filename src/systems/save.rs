@@ -0,0 +1,89 @@
+use bevy::{app::AppExit, prelude::*, window::WindowCloseRequested};
+
+use super::board::{Game, GameClock, GameDealt, MovePlayed, MoveUndone};
+use super::score::ScoreSettings;
+use crate::rules::save::{self, SaveFile, SAVE_VERSION};
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        // after Update, so a new game has already been dealt by the time it gets saved
+        app.add_systems(PostUpdate, (autosave, save_on_exit));
+    }
+}
+
+/// The saved game, if there is one and it loads. A save that won't load gets moved aside to
+/// save.ron.bad so the next autosave doesn't quietly throw it away.
+pub fn restore() -> Option<(Game, GameClock, ScoreSettings)> {
+    let path = save::save_path()?;
+    match save::read(&path) {
+        Ok(Some(file)) => {
+            info!("Restoring deal {} from {}", file.deal, path.display());
+            Some((
+                Game {
+                    state: file.state,
+                    deal: file.deal,
+                    history: file.history,
+                },
+                GameClock {
+                    elapsed: file.elapsed_secs,
+                },
+                file.scoring,
+            ))
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Not restoring {}: {}", path.display(), e);
+            let aside = path.with_extension("ron.bad");
+            if let Err(e) = std::fs::rename(&path, &aside) {
+                error!("Couldn't move the bad save to {}: {}", aside.display(), e);
+            }
+            None
+        }
+    }
+}
+
+fn write_save(game: &Game, clock: &GameClock, scoring: &ScoreSettings) {
+    let Some(path) = save::save_path() else {
+        return;
+    };
+    let file = SaveFile {
+        version: SAVE_VERSION,
+        deal: game.deal,
+        state: game.state.clone(),
+        history: game.history.clone(),
+        scoring: *scoring,
+        elapsed_secs: clock.elapsed,
+    };
+    if let Err(e) = save::write(&path, &file) {
+        error!("Couldn't save the game to {}: {}", path.display(), e);
+    }
+}
+
+fn autosave(
+    game: Res<Game>,
+    clock: Res<GameClock>,
+    scoring: Res<ScoreSettings>,
+    mut played: EventReader<MovePlayed>,
+    mut undone: EventReader<MoveUndone>,
+    mut new_games: EventReader<GameDealt>,
+) {
+    let moves = played.iter().count() + undone.iter().count() + new_games.iter().count();
+    if moves > 0 {
+        write_save(&game, &clock, &scoring);
+    }
+}
+
+fn save_on_exit(
+    game: Res<Game>,
+    clock: Res<GameClock>,
+    scoring: Res<ScoreSettings>,
+    mut closing: EventReader<WindowCloseRequested>,
+    mut exiting: EventReader<AppExit>,
+) {
+    let exits = closing.iter().count() + exiting.iter().count();
+    if exits > 0 {
+        write_save(&game, &clock, &scoring);
+    }
+}
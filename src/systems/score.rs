@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+
+use super::board::{Game, GameDealt, MovePlayed, MoveUndone};
+use crate::rules::{save, scoring::Scoring};
+
+pub use crate::rules::scoring::ScoreSettings;
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScoreSettings>()
+            .init_resource::<Score>()
+            .add_systems(Startup, load_bankroll)
+            // after Update like the autosave, so a new game has been dealt before it's scored
+            .add_systems(PostUpdate, keep_score);
+    }
+}
+
+/// How the game is being scored, picked on the command line or picked back up from the save.
+impl Resource for ScoreSettings {}
+
+#[derive(Resource, Default)]
+pub struct Score {
+    /// Points for the current game, the deal and every move still standing. Time penalties get
+    /// worked out when it's shown.
+    pub points: i32,
+    /// The bankroll with the game in play counted in, only used when scoring is cumulative.
+    pub banked: i32,
+}
+
+impl Score {
+    pub fn total(&self, settings: &ScoreSettings, elapsed_secs: f32) -> i32 {
+        settings.scoring.total(self.points, elapsed_secs)
+    }
+
+    /// The score the way the HUD shows it, money for Vegas.
+    pub fn text(&self, settings: &ScoreSettings, elapsed_secs: f32) -> String {
+        let total = self.total(settings, elapsed_secs);
+        match settings.scoring {
+            Scoring::Standard => format!("Score {}", total),
+            Scoring::Vegas if settings.cumulative => {
+                format!("{}    Bankroll {}", dollars(total), dollars(self.banked))
+            }
+            Scoring::Vegas => dollars(total),
+        }
+    }
+}
+
+fn dollars(amount: i32) -> String {
+    if amount < 0 {
+        format!("-${}", -amount)
+    } else {
+        format!("${}", amount)
+    }
+}
+
+/// Loaded whatever the scoring, a restored game can bring cumulative scoring back with it.
+fn load_bankroll(mut score: ResMut<Score>) {
+    let Some(path) = save::bankroll_path() else {
+        return;
+    };
+    match save::read_bankroll(&path) {
+        Ok(bankroll) => score.banked = bankroll.banked,
+        Err(e) => warn!("Starting a fresh bankroll, {}: {}", path.display(), e),
+    }
+}
+
+fn write_bankroll(banked: i32) {
+    let Some(path) = save::bankroll_path() else {
+        return;
+    };
+    if let Err(e) = save::write_bankroll(&path, &save::Bankroll { banked }) {
+        error!("Couldn't save the bankroll to {}: {}", path.display(), e);
+    }
+}
+
+/// Adds up the same move events everything else reacts to. A brand new or restored game gets
+/// scored from its history instead.
+fn keep_score(
+    settings: Res<ScoreSettings>,
+    game: Res<Game>,
+    mut score: ResMut<Score>,
+    mut played: EventReader<MovePlayed>,
+    mut undone: EventReader<MoveUndone>,
+    mut new_games: EventReader<GameDealt>,
+) {
+    let scoring = settings.scoring;
+    let new_game = new_games.iter().count() > 0;
+    let before = score.points;
+    if new_game || game.is_added() {
+        score.points = scoring.points_for(game.history.played());
+        played.clear();
+        undone.clear();
+    } else {
+        for MovePlayed(applied) in played.iter() {
+            score.points += scoring.points(applied);
+        }
+        for MoveUndone(applied) in undone.iter() {
+            score.points -= scoring.points(applied);
+        }
+    }
+    if !settings.cumulative {
+        return;
+    }
+    let change = bankroll_change(new_game, game.is_added(), before, score.points);
+    if change != 0 {
+        score.banked += change;
+        write_bankroll(score.banked);
+    }
+}
+
+/// What the bankroll moves by when the game in play goes from `before` points to `after`. Money
+/// changes hands as the game goes rather than when it ends, so a game that gets quit or replaced
+/// partway through has already paid for its deal.
+fn bankroll_change(new_game: bool, restored: bool, before: i32, after: i32) -> i32 {
+    if new_game {
+        // whatever the old game made or lost is in already, the new one starts with its deal
+        after
+    } else if restored {
+        // the saved game was counted in as it was played
+        0
+    } else {
+        after - before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_bankroll_pays_for_a_deal_straight_away() {
+        let vegas = Scoring::Vegas;
+        let mut banked = 100;
+        banked += bankroll_change(true, true, 0, vegas.deal_points());
+        assert_eq!(banked, 48);
+        // two cards up, then quitting: nothing's left to settle
+        banked += bankroll_change(false, false, -52, -42);
+        assert_eq!(banked, 58);
+        // picking the save back up doesn't charge for it again
+        banked += bankroll_change(false, true, 0, -42);
+        assert_eq!(banked, 58);
+        // and neither does dealing over it, the next deal is all that gets charged
+        banked += bankroll_change(true, false, -42, vegas.deal_points());
+        assert_eq!(banked, 6);
+    }
+}
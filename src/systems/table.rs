@@ -0,0 +1,262 @@
+//! Fitting the board to the window. The piles are placed again whenever the window changes shape,
+//! spread further apart when there's width to spare, and the camera zooms so the whole board
+//! shows with a margin round it. The cards follow their piles over through the board sync.
+
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, WindowResized},
+};
+
+use super::board::{Game, TABLEAU_FAN};
+use crate::components::cards::{CardSlot, CARD_SIZE};
+use crate::rules::variant::{Fan, Layout, PileSlot};
+use crate::rules::PileId;
+use crate::CardSlotPositions;
+
+pub struct TablePlugin;
+
+impl Plugin for TablePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LayoutMode>()
+            .add_systems(Update, fit_to_window);
+    }
+}
+
+/// Which way round the board is laid out, `--layout` on the command line.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LayoutMode {
+    /// Portrait when the window is taller than it's wide, landscape otherwise.
+    #[default]
+    Auto,
+    /// Columns spread out to use the width, with room kept under the tableau for it to fan down.
+    Landscape,
+    /// The foundations get rows of their own above the tableau and the columns pack tight, so the
+    /// cards come out as big as the width allows. Everything under the board is left for the
+    /// tableau.
+    Portrait,
+}
+
+impl LayoutMode {
+    fn portrait(self, window: Vec2) -> bool {
+        match self {
+            LayoutMode::Auto => window.y > window.x,
+            LayoutMode::Landscape => false,
+            LayoutMode::Portrait => true,
+        }
+    }
+}
+
+/// Gap between piles, they never sit closer than this.
+const PADDING: f32 = 7.0;
+/// However wide the window is the columns don't spread further apart than this.
+const MAX_GAP: f32 = 40.0;
+/// Space kept round the edge of the board.
+const MARGIN: f32 = 12.0;
+/// How many fanned face up tableau cards a landscape layout keeps room for, longer columns
+/// squeeze up.
+const FAN_ROOM: f32 = 12.0;
+
+/// Where the piles of a layout go for one window size, and how the camera looks at them.
+#[derive(Clone, Debug)]
+pub struct Placement {
+    /// One for each slot of the layout, in the same order.
+    pub positions: Vec<Vec2>,
+    pub camera: Vec2,
+    /// The camera's projection scale, world units per pixel.
+    pub scale: f32,
+    /// How low a card's centre goes before it's off the bottom of the window.
+    pub floor: f32,
+}
+
+/// Centres the board across the window with its top row pinned to the top, so a tableau has
+/// everything underneath to grow into.
+pub fn place(layout: &Layout, window: Vec2, mode: LayoutMode) -> Placement {
+    let portrait = mode.portrait(window);
+    let layout = &if portrait {
+        portrait_layout(layout)
+    } else {
+        layout.clone()
+    };
+    let columns = layout.slots.iter().map(|s| s.column).fold(0.0, f32::max);
+    let rows = layout.slots.iter().map(|s| s.row).fold(0.0, f32::max);
+    let mut height = CARD_SIZE.y + rows * (CARD_SIZE.y + PADDING) + 2.0 * MARGIN;
+    if !portrait && layout.slots.iter().any(|s| s.fan == Fan::Down) {
+        height += FAN_ROOM * TABLEAU_FAN;
+    }
+    let tight = CARD_SIZE.x + columns * (CARD_SIZE.x + PADDING) + 2.0 * MARGIN;
+    // a window wider than the board needs spreads the columns out instead of leaving the extra
+    // width at the sides
+    let gap = if portrait || columns == 0.0 {
+        PADDING
+    } else {
+        let spare = height * window.x / window.y - tight;
+        (PADDING + spare.max(0.0) / columns).min(MAX_GAP)
+    };
+    let step = Vec2::new(CARD_SIZE.x + gap, CARD_SIZE.y + PADDING);
+    let width = CARD_SIZE.x + columns * step.x + 2.0 * MARGIN;
+    let positions = layout
+        .slots
+        .iter()
+        .map(|s| Vec2::new((s.column - columns / 2.0) * step.x, -s.row * step.y))
+        .collect();
+    let scale = (width / window.x).max(height / window.y);
+    let top = CARD_SIZE.y / 2.0 + MARGIN;
+    let bottom = top - window.y * scale;
+    Placement {
+        positions,
+        camera: Vec2::new(0.0, top - window.y * scale / 2.0),
+        scale,
+        floor: bottom + CARD_SIZE.y / 2.0 + MARGIN,
+    }
+}
+
+/// `layout` for a window taller than it's wide. The foundations come down off the top row onto
+/// rows of their own just above the tableau, lined up with its right hand end and no wider than
+/// it, so a top row wider than the tableau stops setting how wide the board is. Layouts without a
+/// fanned tableau stay as they are.
+fn portrait_layout(layout: &Layout) -> Layout {
+    let tableau = layout.slots.iter().filter(|s| s.fan == Fan::Down);
+    let Some(tableau_row) = tableau.clone().map(|s| s.row).reduce(f32::min) else {
+        return layout.clone();
+    };
+    let across = tableau.map(|s| s.column as usize + 1).max().unwrap_or(1);
+    let above = |s: &PileSlot| s.row < tableau_row;
+    let moves = |s: &PileSlot| above(s) && matches!(s.pile, PileId::Foundation(_));
+    let foundations = layout.slots.iter().filter(|s| moves(s)).count();
+    // the row under whatever stays up top
+    let first = layout
+        .slots
+        .iter()
+        .filter(|s| above(s) && !moves(s))
+        .map(|s| s.row + 1.0)
+        .fold(0.0, f32::max);
+    let shift = first + foundations.div_ceil(across) as f32 - tableau_row;
+    let mut placed = 0;
+    let slots = layout
+        .slots
+        .iter()
+        .map(|&s| {
+            if moves(&s) {
+                let (row, i) = (placed / across, placed % across);
+                let in_row = across.min(foundations - row * across);
+                placed += 1;
+                PileSlot {
+                    column: (across - in_row + i) as f32,
+                    row: first + row as f32,
+                    ..s
+                }
+            } else if above(&s) {
+                s
+            } else {
+                PileSlot {
+                    row: s.row + shift,
+                    ..s
+                }
+            }
+        })
+        .collect();
+    Layout { slots }
+}
+
+/// The window's size, or None while it's minimised and there's nothing to fit.
+pub fn window_size(window: &Window) -> Option<Vec2> {
+    let size = Vec2::new(window.width(), window.height());
+    (size.min_element() >= 1.0).then_some(size)
+}
+
+/// Runs the first time round too, the layout mode reads as changed then, which zooms the camera
+/// in on the board `generate_board` placed.
+fn fit_to_window(
+    mut resized: EventReader<WindowResized>,
+    mode: Res<LayoutMode>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    game: Res<Game>,
+    mut slots: ResMut<CardSlotPositions>,
+    mut slot_txs: Query<&mut Transform, (With<CardSlot>, Without<Camera2d>)>,
+    mut camera: Query<(&mut OrthographicProjection, &mut Transform), With<Camera2d>>,
+) {
+    if resized.iter().count() == 0 && !mode.is_changed() {
+        return;
+    }
+    let Some(size) = window.get_single().ok().and_then(window_size) else {
+        return;
+    };
+    let placement = place(&game.state.variant().layout(), size, *mode);
+    if let Ok((mut projection, mut cam_tx)) = camera.get_single_mut() {
+        projection.scale = placement.scale;
+        cam_tx.translation.x = placement.camera.x;
+        cam_tx.translation.y = placement.camera.y;
+    }
+    let moved = slots.floor != placement.floor
+        || slots
+            .slots
+            .iter()
+            .zip(placement.positions.iter())
+            .any(|(slot, p)| slot.position != *p);
+    // leaves the slots unchanged, the board sync only sends the cards over when they've moved
+    if !moved {
+        return;
+    }
+    // a shorter window squeezes the tableau columns up even when the piles stay put
+    slots.floor = placement.floor;
+    for (slot, position) in slots.slots.iter_mut().zip(placement.positions) {
+        slot.position = position;
+        if let Ok(mut tx) = slot_txs.get_mut(slot.entity) {
+            tx.translation = position.extend(0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::variant::Variant;
+
+    #[test]
+    fn a_tall_window_gets_the_portrait_positions() {
+        let layout = Variant::Klondike(Default::default()).layout();
+        let tall = place(&layout, Vec2::new(600.0, 1000.0), LayoutMode::Auto);
+        let portrait = place(&layout, Vec2::new(600.0, 1000.0), LayoutMode::Portrait);
+        let wide = place(&layout, Vec2::new(1000.0, 600.0), LayoutMode::Auto);
+        assert_eq!(tall.positions, portrait.positions);
+        // stock, first foundation, first tableau column
+        let y = |placement: &Placement| [0, 2, 6].map(|i| placement.positions[i].y);
+        let [stock, foundation, tableau] = y(&wide);
+        assert_eq!(stock, foundation);
+        assert!(tableau < foundation);
+        let [stock, foundation, tableau] = y(&tall);
+        assert!(foundation < stock);
+        assert!(tableau < foundation);
+    }
+
+    #[test]
+    fn portrait_brings_the_foundations_in_to_the_tableau() {
+        // eight foundations across the top of a seven column tableau
+        let mut slots = vec![
+            PileSlot::new(PileId::Stock, 0.0, 0.0, Fan::Stacked),
+            PileSlot::new(PileId::Waste, 1.0, 0.0, Fan::Stacked),
+        ];
+        slots.extend(
+            (0..8).map(|i| PileSlot::new(PileId::Foundation(i), 2.0 + i as f32, 0.0, Fan::Stacked)),
+        );
+        slots.extend((0..7).map(|i| PileSlot::new(PileId::Tableau(i), i as f32, 1.0, Fan::Down)));
+        let layout = portrait_layout(&Layout { slots });
+        let widest = layout.slots.iter().map(|s| s.column).fold(0.0, f32::max);
+        assert_eq!(widest, 6.0);
+        let at = |pile| {
+            let slot = layout.slots.iter().find(|s| s.pile == pile).unwrap();
+            (slot.column, slot.row)
+        };
+        assert_eq!(at(PileId::Stock), (0.0, 0.0));
+        assert_eq!(at(PileId::Foundation(0)), (0.0, 1.0));
+        assert_eq!(at(PileId::Foundation(6)), (6.0, 1.0));
+        assert_eq!(at(PileId::Foundation(7)), (6.0, 2.0));
+        assert_eq!(at(PileId::Tableau(0)), (0.0, 3.0));
+    }
+
+    #[test]
+    fn layouts_without_a_tableau_stay_put_in_portrait() {
+        let layout = Variant::Overlap(crate::rules::overlap::Rules::Pyramid).layout();
+        assert_eq!(portrait_layout(&layout), layout);
+    }
+}
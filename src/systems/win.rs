@@ -0,0 +1,222 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+use rand::Rng;
+
+use super::board::{Dealing, Game, GameClock, GameWon, NewGame};
+use super::score::{Score, ScoreSettings};
+use crate::components::cards::{CardFront, CardVisual, CARD_SIZE};
+use crate::rules::PileId;
+use crate::MoveThisCard;
+
+pub struct WinPlugin;
+
+impl Plugin for WinPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Cascade>().add_systems(
+            Update,
+            (
+                (start_cascade, launch_cards, bounce_cards).chain(),
+                end_cascade,
+                win_summary,
+            ),
+        );
+    }
+}
+
+/// A card thrown off the foundations once the game is won. It falls, bounces off the bottom of
+/// the screen and leaves a trail behind until it goes off the side. Cards that get a
+/// `MoveThisCard` stop bouncing, the board wants them back.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct BouncingCard {
+    velocity: Vec2,
+    /// Seconds until the next trail sprite gets left behind.
+    next_trail: f32,
+    /// How deep it's drawn, the later it was thrown the higher.
+    depth: f32,
+}
+
+/// A copy of a bouncing card's face left where it was, cleared away with the next game.
+#[derive(Component)]
+struct CascadeTrail;
+
+/// Cards still to be thrown, by `Card::id`, the next one last.
+#[derive(Resource, Default)]
+struct Cascade {
+    waiting: Vec<usize>,
+    launched: usize,
+    next_launch: f32,
+}
+
+const GRAVITY: f32 = 1500.0;
+/// How much of its speed a card keeps after hitting the floor.
+const BOUNCE: f32 = 0.8;
+const LAUNCH_GAP: f32 = 0.25;
+const TRAIL_GAP: f32 = 0.03;
+/// Launched cards are drawn above everything else on the board while they fly.
+const CASCADE_Z: f32 = 2000.0;
+
+fn start_cascade(
+    mut won: EventReader<GameWon>,
+    game: Res<Game>,
+    time: Res<Time>,
+    mut cascade: ResMut<Cascade>,
+) {
+    if won.iter().count() == 0 {
+        return;
+    }
+    // kings first, going round the foundations the way the old Windows one did
+    let foundations: Vec<_> = game
+        .state
+        .piles()
+        .into_iter()
+        .filter(|p| matches!(p, PileId::Foundation(_)))
+        .filter_map(|p| game.state.pile(p))
+        .collect();
+    cascade.waiting = (0..13)
+        .flat_map(|rank| foundations.iter().rev().filter_map(move |f| f.get(rank)))
+        .map(|pc| pc.card.id())
+        .collect();
+    cascade.launched = 0;
+    cascade.next_launch = time.elapsed_seconds();
+}
+
+fn launch_cards(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut cascade: ResMut<Cascade>,
+    visuals: Query<(Entity, &CardVisual, Option<&MoveThisCard>), Without<BouncingCard>>,
+) {
+    let now = time.elapsed_seconds();
+    let Some(&next) = cascade.waiting.last() else {
+        return;
+    };
+    if now < cascade.next_launch {
+        return;
+    }
+    let Some((visual, _, moving)) = visuals.iter().find(|(_, c, _)| c.id() == next) else {
+        cascade.waiting.pop();
+        return;
+    };
+    // the last few cards of an auto-complete can still be on their way up
+    if moving.is_some() {
+        return;
+    }
+    cascade.waiting.pop();
+    cascade.next_launch = now + LAUNCH_GAP;
+    cascade.launched += 1;
+    let mut rng = rand::thread_rng();
+    let speed = rng.gen_range(150.0..350.0);
+    let direction = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+    commands.entity(visual).insert(BouncingCard {
+        velocity: Vec2::new(speed * direction, rng.gen_range(0.0..300.0)),
+        next_trail: 0.0,
+        depth: CASCADE_Z + cascade.launched as f32,
+    });
+}
+
+fn bounce_cards(
+    mut commands: Commands,
+    time: Res<Time>,
+    camera: Query<(&OrthographicProjection, &GlobalTransform), With<Camera2d>>,
+    mut cards: Query<(Entity, &mut Transform, &mut BouncingCard, &Children), Without<MoveThisCard>>,
+    fronts: Query<(&Handle<TextureAtlas>, &TextureAtlasSprite), With<CardFront>>,
+) {
+    let Ok((projection, cam_tx)) = camera.get_single() else {
+        return;
+    };
+    let screen = Rect {
+        min: projection.area.min + cam_tx.translation().truncate(),
+        max: projection.area.max + cam_tx.translation().truncate(),
+    };
+    let dt = time.delta_seconds();
+    for (e, mut tx, mut bouncing, children) in cards.iter_mut() {
+        bouncing.velocity.y -= GRAVITY * dt;
+        tx.translation += (bouncing.velocity * dt).extend(0.0);
+        tx.translation.z = bouncing.depth;
+        let floor = screen.min.y + CARD_SIZE.y / 2.0;
+        if tx.translation.y < floor {
+            tx.translation.y = floor;
+            bouncing.velocity.y = -bouncing.velocity.y * BOUNCE;
+        }
+        if tx.translation.x < screen.min.x - CARD_SIZE.x
+            || tx.translation.x > screen.max.x + CARD_SIZE.x
+        {
+            commands.entity(e).remove::<BouncingCard>();
+            continue;
+        }
+        bouncing.next_trail -= dt;
+        if bouncing.next_trail > 0.0 {
+            continue;
+        }
+        bouncing.next_trail = TRAIL_GAP;
+        let Some((atlas, sprite)) = children.iter().find_map(|c| fronts.get(*c).ok()) else {
+            continue;
+        };
+        // just under the card that left it
+        commands.spawn((
+            SpriteSheetBundle {
+                texture_atlas: atlas.clone(),
+                sprite: TextureAtlasSprite {
+                    index: sprite.index,
+                    ..default()
+                },
+                transform: Transform::from_translation(tx.translation - Vec3::Z * 0.5),
+                ..default()
+            },
+            CascadeTrail,
+        ));
+    }
+}
+
+/// Once the game isn't won any more, a new deal or an undo, the cascade is over and the cards
+/// are left to the board sync.
+fn end_cascade(
+    mut commands: Commands,
+    game: Res<Game>,
+    mut cascade: ResMut<Cascade>,
+    bouncing: Query<Entity, With<BouncingCard>>,
+    trails: Query<Entity, With<CascadeTrail>>,
+) {
+    if !game.is_changed() || game.state.is_won() {
+        return;
+    }
+    cascade.waiting.clear();
+    for e in bouncing.iter() {
+        commands.entity(e).remove::<BouncingCard>();
+    }
+    for e in trails.iter() {
+        commands.entity(e).despawn();
+    }
+}
+
+fn win_summary(
+    mut contexts: EguiContexts,
+    game: Option<Res<Game>>,
+    clock: Res<GameClock>,
+    score: Res<Score>,
+    score_settings: Res<ScoreSettings>,
+    dealing: Option<Res<Dealing>>,
+    mut new_game: EventWriter<NewGame>,
+) {
+    let Some(game) = game else {
+        return;
+    };
+    // gone as soon as the next game's asked for, while it's dealt the HUD says so
+    if !game.state.is_won() || dealing.is_some() {
+        return;
+    }
+    egui::Window::new("You won!")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .collapsible(false)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let secs = clock.elapsed as u32;
+            ui.label(format!("Deal {}", game.deal));
+            ui.label(format!("Time {}:{:02}", secs / 60, secs % 60));
+            ui.label(format!("Moves {}", game.history.moves_played()));
+            ui.label(score.text(&score_settings, clock.elapsed));
+            if ui.button("New game").clicked() {
+                new_game.send(NewGame);
+            }
+        });
+}